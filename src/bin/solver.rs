@@ -0,0 +1,104 @@
+// Finds launch vectors that beat a level
+//
+// Usage: solver <file.level.ron> [--angles N] [--powers N] [--min-power X] [--max-power X] [--seconds S]
+//
+// Exits with 1 if the level can't be solved, so it can be used to check levels on CI
+
+use bevy_orbit::solver::{SolverSettings, solve_level_file};
+use std::process::ExitCode;
+
+// Powers and times can't go backwards, NaN and infinity aren't much use either
+fn number(args: &mut impl Iterator<Item = String>, name: &str) -> Option<f32> {
+    let parsed = args
+        .next()
+        .and_then(|value| value.parse().ok())
+        .filter(|value: &f32| value.is_finite() && *value >= 0.0);
+
+    if parsed.is_none() {
+        eprintln!("Expected a number of at least 0 after {name}");
+    }
+
+    parsed
+}
+
+// Step counts have to be whole and there has to be at least one
+fn steps(args: &mut impl Iterator<Item = String>, name: &str) -> Option<u32> {
+    let parsed = args
+        .next()
+        .and_then(|value| value.parse().ok())
+        .filter(|&value| value > 0);
+
+    if parsed.is_none() {
+        eprintln!("Expected a whole number above 0 after {name}");
+    }
+
+    parsed
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+
+    let mut path = None;
+    let mut settings = SolverSettings::default();
+
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--angles" => steps(&mut args, &arg).map(|value| settings.angle_steps = value),
+            "--powers" => steps(&mut args, &arg).map(|value| settings.power_steps = value),
+            "--min-power" => number(&mut args, &arg).map(|value| settings.min_power = value),
+            "--max-power" => number(&mut args, &arg).map(|value| settings.max_power = value),
+            "--seconds" => number(&mut args, &arg).map(|value| settings.max_seconds = value),
+            _ if path.is_none() && !arg.starts_with("--") => {
+                path = Some(arg);
+                Some(())
+            }
+            _ => {
+                eprintln!("Unexpected argument '{arg}'");
+                None
+            }
+        };
+
+        if parsed.is_none() {
+            return ExitCode::from(2);
+        }
+    }
+
+    if settings.min_power > settings.max_power {
+        eprintln!("--min-power can't be more than --max-power");
+        return ExitCode::from(2);
+    }
+
+    let Some(path) = path else {
+        eprintln!(
+            "Usage: solver <file.level.ron> [--angles N] [--powers N] [--min-power X] [--max-power X] [--seconds S]"
+        );
+        return ExitCode::from(2);
+    };
+
+    match solve_level_file(&path, &settings) {
+        Ok(solutions) if solutions.is_empty() => {
+            println!("No solutions found for '{path}'");
+            ExitCode::FAILURE
+        }
+        Ok(solutions) => {
+            println!("Found {} solutions for '{path}':", solutions.len());
+
+            for solution in solutions {
+                println!(
+                    "  angle {:>6.1}°  power {:>6.1}  velocity ({:.1}, {:.1})  {:.2}s",
+                    solution.angle.to_degrees(),
+                    solution.power,
+                    solution.velocity.x,
+                    solution.velocity.y,
+                    solution.seconds(),
+                );
+            }
+
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Failed to solve '{path}': {err}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -29,6 +29,18 @@ impl Default for GridSettings {
     }
 }
 
+impl GridSettings {
+    // The space between the lower lines at this camera scale
+    pub fn spacing(&self, scale: f32) -> Vec2 {
+        let recursive_depth = self.recursive_depth as f32;
+
+        // This allows for the flooring of the linear scale at exponential rates
+        let floored_scaling = recursive_depth.powf(scale.log(recursive_depth).floor());
+
+        floored_scaling * self.cell_size
+    }
+}
+
 pub fn grid_settings_ui(ui: &mut egui::Ui, grid_settings: &mut GridSettings) {
     let mut lower_egui_hsva = bevy_hsva_to_egui_hsva(grid_settings.lower_color);
     let mut upper_egui_hsva = bevy_hsva_to_egui_hsva(grid_settings.upper_color);
@@ -90,11 +102,8 @@ pub fn draw_grid(
     let grid_settings = &debug_settings.grid_settings;
     let recursive_depth: f32 = grid_settings.recursive_depth as f32;
 
-    // This allows for the flooring of the linear scale at exponential rates
-    let floored_scaling = recursive_depth.powf(scale.log(recursive_depth).floor());
-
     // The space between every line of the grid
-    let grid_spacing = floored_scaling * grid_settings.cell_size;
+    let grid_spacing = grid_settings.spacing(scale);
 
     let camera_xy = camera_transform.translation.xy();
 
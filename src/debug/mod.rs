@@ -66,6 +66,11 @@ impl DebugSettings {
     pub fn toggle_inspector(&mut self) {
         self.show_inspector = !self.show_inspector;
     }
+
+    /// The smallest grid cell that's drawn at this camera scale, even if the grid is hidden
+    pub fn grid_spacing(&self, scale: f32) -> Vec2 {
+        self.grid_settings.spacing(scale)
+    }
 }
 
 pub fn toggle_debug_ui(mut settings: ResMut<DebugSettings>) {
@@ -79,6 +84,9 @@ pub fn debug_ui(
         (&mut Transform, &mut crate::camera::CameraVelocity),
         With<crate::camera::GameCamera>,
     >,
+    mut gravity_solver: ResMut<crate::game::gravity::GravitySolver>,
+    mut trajectory_prediction: ResMut<crate::game::prediction::TrajectoryPrediction>,
+    mut orbit_readout: ResMut<crate::game::orbit::OrbitReadoutSettings>,
 ) {
     let mut debug_settings = debug_settings;
 
@@ -99,7 +107,49 @@ pub fn debug_ui(
         ui.collapsing("Camera Settings", |ui| {
             camera_settings_ui(ui, &mut *camera.0, &mut *camera.1);
         });
+        ui.collapsing("Gravity Settings", |ui| {
+            gravity_solver_ui(ui, &mut gravity_solver);
+        });
+        ui.collapsing("Trajectory Prediction", |ui| {
+            // How far ahead is up to the level, it's set in the editor
+            ui.checkbox(&mut trajectory_prediction.enabled, "Show prediction");
+        });
+        ui.collapsing("Orbit Readout", |ui| {
+            ui.checkbox(&mut orbit_readout.show_readout, "Show orbital elements");
+            ui.checkbox(&mut orbit_readout.show_conic, "Draw orbit");
+            ui.checkbox(
+                &mut orbit_readout.show_influence,
+                "Draw spheres of influence",
+            );
+        });
+    });
+}
+
+fn gravity_solver_ui(ui: &mut egui::Ui, solver: &mut crate::game::gravity::GravitySolver) {
+    use crate::game::gravity::GravitySolver;
+
+    ui.label("Solver");
+    ui.horizontal(|ui| {
+        if ui.radio(*solver == GravitySolver::Exact, "Exact").clicked() {
+            *solver = GravitySolver::Exact;
+        }
+
+        if ui
+            .radio(
+                matches!(solver, GravitySolver::BarnesHut { .. }),
+                "Barnes-Hut",
+            )
+            .clicked()
+            && *solver == GravitySolver::Exact
+        {
+            *solver = GravitySolver::barnes_hut();
+        }
     });
+
+    if let GravitySolver::BarnesHut { theta } = solver {
+        ui.label("Opening angle (θ)");
+        ui.add(egui::Slider::new(theta, 0.0..=1.5));
+    }
 }
 
 fn camera_settings_ui(
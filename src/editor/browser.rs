@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::serialization::backups::{Backup, list_backups};
+use serde::{Deserialize, de::IgnoredAny};
+
+// Same root the asset server reads levels from
+const ASSETS_DIR: &str = "assets";
+
+// Levels saved before they got their own extension are still `.scn.ron`
+const LEVEL_EXTENSIONS: &[&str] = &[".level.ron", ".scn.ron"];
+
+/// Every level under `assets/`, only rescanned when asked so the ui doesn't hit the disk every frame
+#[derive(Resource, Default)]
+pub struct LevelBrowser {
+    levels: Vec<LevelEntry>,
+    scanned: bool,
+    // The backups of one level, relative to `assets/` like everything else here
+    backups: Option<(PathBuf, Vec<Backup>)>,
+}
+
+pub struct LevelEntry {
+    // Relative to `assets/`, ready for [crate::serialization::LevelSerializationData]
+    pub path: PathBuf,
+    pub name: String,
+    // None if the file couldn't be read
+    pub entity_count: Option<usize>,
+    pub modified: Option<SystemTime>,
+}
+
+impl LevelBrowser {
+    pub fn refresh(&mut self) {
+        self.levels.clear();
+
+        find_levels(Path::new(ASSETS_DIR), &mut self.levels);
+
+        self.levels.sort_by(|a, b| a.path.cmp(&b.path));
+        self.scanned = true;
+    }
+
+    pub fn levels(&mut self) -> &[LevelEntry] {
+        if !self.scanned {
+            self.refresh();
+        }
+
+        &self.levels
+    }
+
+    /// The backups of `level`, only listed again when a different level is asked for or after [LevelBrowser::forget_backups]
+    pub fn backups(&mut self, level: &Path) -> &[Backup] {
+        self.backups
+            .take_if(|(cached, _)| cached.as_path() != level);
+
+        let (_, backups) = self.backups.get_or_insert_with(|| {
+            (
+                level.to_path_buf(),
+                list_backups(&Path::new(ASSETS_DIR).join(level)),
+            )
+        });
+
+        backups
+    }
+
+    pub fn forget_backups(&mut self) {
+        self.backups = None;
+    }
+}
+
+// Saves and restores both make new backups
+pub fn forget_backups(mut browser: ResMut<LevelBrowser>) {
+    browser.forget_backups();
+}
+
+fn find_levels(directory: &Path, levels: &mut Vec<LevelEntry>) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!("Failed to read directory '{}': {err}", directory.display());
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            find_levels(&path, levels);
+            continue;
+        }
+
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| {
+                LEVEL_EXTENSIONS
+                    .iter()
+                    .find_map(|extension| name.strip_suffix(extension))
+            })
+        else {
+            continue;
+        };
+
+        let entity_count = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|source| count_entities(&source));
+
+        levels.push(LevelEntry {
+            name: name.to_string(),
+            entity_count,
+            modified: entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+            path: path
+                .strip_prefix(ASSETS_DIR)
+                .map(Path::to_path_buf)
+                .unwrap_or(path),
+        });
+    }
+}
+
+// Doesn't need the type registry, the components are skipped over
+fn count_entities(source: &str) -> Option<usize> {
+    #[derive(Deserialize)]
+    struct Entities {
+        entities: HashMap<u64, IgnoredAny>,
+    }
+
+    ron::from_str::<Entities>(source)
+        .ok()
+        .map(|level| level.entities.len())
+}
+
+fn format_modified(modified: Option<SystemTime>) -> String {
+    let Some(elapsed) = modified.and_then(|modified| modified.elapsed().ok()) else {
+        return "unknown".to_string();
+    };
+
+    let seconds = elapsed.as_secs();
+
+    if seconds < 60 {
+        "just now".to_string()
+    } else if seconds < 60 * 60 {
+        format!("{} min ago", seconds / 60)
+    } else if seconds < 60 * 60 * 24 {
+        format!("{} h ago", seconds / (60 * 60))
+    } else {
+        format!("{} days ago", seconds / (60 * 60 * 24))
+    }
+}
+
+/// Lists every level, returns the one that was clicked
+pub fn level_browser_ui(ui: &mut egui::Ui, browser: &mut LevelBrowser) -> Option<PathBuf> {
+    let mut clicked = None;
+
+    if ui.button("Refresh").clicked() {
+        browser.refresh();
+    }
+
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            for level in browser.levels() {
+                let entity_count = level
+                    .entity_count
+                    .map_or("?".to_string(), |count| count.to_string());
+
+                let response = ui
+                    .button(level.name.as_str())
+                    .on_hover_text(level.path.display().to_string());
+
+                ui.label(format!(
+                    "{entity_count} entities, modified {}",
+                    format_modified(level.modified)
+                ));
+
+                if response.clicked() {
+                    clicked = Some(level.path.clone());
+                }
+
+                ui.separator();
+            }
+        });
+
+    clicked
+}
+
+/// The backups of the level at `level`, returns the one to restore if one was picked
+pub fn backup_list_ui(
+    ui: &mut egui::Ui,
+    browser: &mut LevelBrowser,
+    level: &Path,
+) -> Option<PathBuf> {
+    let mut restore = None;
+
+    let backups = browser.backups(level);
+
+    if backups.is_empty() {
+        ui.label("No backups of this level yet");
+    }
+
+    for backup in backups {
+        ui.horizontal(|ui| {
+            ui.label(format_modified(Some(backup.created)));
+
+            if ui.button("Restore").clicked() {
+                restore = Some(backup.path.clone());
+            }
+        });
+    }
+
+    restore
+}
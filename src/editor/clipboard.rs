@@ -0,0 +1,228 @@
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
+use bevy_egui::{EguiContexts, egui};
+
+use super::{
+    history::{History, outside_references},
+    selection::Selected,
+    toasts::Toasts,
+};
+use crate::{
+    cursor::CursorPosition,
+    serialization::{
+        editor::{level_scene_builder, serialize_scene},
+        game::deserialize_level,
+        migration::LevelFormat,
+        serializable_components_type_registry,
+    },
+};
+
+// Where a duplicate ends up relative to the original
+const DUPLICATE_OFFSET: Vec2 = Vec2::new(20.0, -20.0);
+
+/// Copy, paste and duplicate waiting on world access, plus text for the system clipboard
+#[derive(Resource, Default)]
+pub struct EditorClipboard {
+    queued: Vec<ClipboardAction>,
+    // Handed to egui next frame, it owns the system clipboard
+    copied: Option<String>,
+}
+
+enum ClipboardAction {
+    Copy(Vec<Entity>),
+    Duplicate(Vec<Entity>),
+    // The text is a ron scene, same format as a level but usually only a few entities
+    Paste(String),
+}
+
+pub fn clipboard_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut contexts: EguiContexts,
+    selected: Query<Entity, With<Selected>>,
+    mut clipboard: ResMut<EditorClipboard>,
+    mut history: ResMut<History>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    if let Some(text) = clipboard.copied.take() {
+        ctx.copy_text(text);
+    }
+
+    // Don't steal shortcuts from text fields
+    if ctx.wants_keyboard_input() {
+        return;
+    }
+
+    let selection: Vec<Entity> = selected.iter().collect();
+
+    // egui turns the platform's copy and paste shortcuts into these
+    let events = ctx.input(|input| input.events.clone());
+
+    for event in events {
+        match event {
+            egui::Event::Copy if !selection.is_empty() => {
+                clipboard
+                    .queued
+                    .push(ClipboardAction::Copy(selection.clone()));
+            }
+            egui::Event::Cut if !selection.is_empty() => {
+                clipboard
+                    .queued
+                    .push(ClipboardAction::Copy(selection.clone()));
+                history.despawn("Cut", selection.iter().copied());
+            }
+            egui::Event::Paste(text) => clipboard.queued.push(ClipboardAction::Paste(text)),
+            _ => {}
+        }
+    }
+
+    if selection.is_empty() {
+        return;
+    }
+
+    if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        history.despawn("Delete", selection);
+    } else if keys.just_pressed(KeyCode::KeyD)
+        && keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+    {
+        clipboard.queued.push(ClipboardAction::Duplicate(selection));
+    }
+}
+
+// Exclusive since copying reads the whole world through reflection, runs before the history
+// so a cut gets copied before it's despawned
+pub fn apply_clipboard(world: &mut World) {
+    let queued = std::mem::take(&mut world.resource_mut::<EditorClipboard>().queued);
+
+    for action in queued {
+        match action {
+            ClipboardAction::Copy(entities) => copy_entities(world, &entities),
+            ClipboardAction::Duplicate(entities) => {
+                let scene = level_scene_builder(world)
+                    .extract_entities(entities.into_iter())
+                    .build();
+
+                // Duplicates keep pointing at the same things as the originals
+                let entity_map = outside_references(world, &scene);
+
+                spawn_fragment(world, scene, entity_map, DUPLICATE_OFFSET, "Duplicate");
+            }
+            ClipboardAction::Paste(text) => paste_text(world, &text),
+        }
+    }
+}
+
+fn copy_entities(world: &mut World, entities: &[Entity]) {
+    // The format version comes along so pasting into a newer build still migrates it
+    let scene = level_scene_builder(world)
+        .allow_resource::<LevelFormat>()
+        .extract_entities(entities.iter().copied())
+        .extract_resources()
+        .build();
+
+    let now = world.resource::<Time>().elapsed_secs();
+
+    match serialize_scene(&scene) {
+        Ok(text) => {
+            world.resource_mut::<EditorClipboard>().copied = Some(text);
+            world.resource_mut::<Toasts>().push(
+                format!("Copied {} entities", scene.entities.len()),
+                false,
+                now,
+            );
+        }
+        Err(err) => {
+            error!("Failed to copy entities: {err}");
+            world
+                .resource_mut::<Toasts>()
+                .push(format!("Copy failed: {err}"), true, now);
+        }
+    }
+}
+
+fn paste_text(world: &mut World, text: &str) {
+    // Same registry as loading, so only types a level can have get through
+    let result = deserialize_level(text, &serializable_components_type_registry());
+
+    let mut scene = match result {
+        Ok(scene) => scene,
+        Err(err) => {
+            // Probably just not a level on the clipboard
+            warn!("Failed to paste: {err}");
+
+            let now = world.resource::<Time>().elapsed_secs();
+            world
+                .resource_mut::<Toasts>()
+                .push(format!("Paste failed: {err}"), true, now);
+            return;
+        }
+    };
+
+    // A whole level pasted in shouldn't replace this one's settings
+    scene.resources.clear();
+
+    // Centered on the cursor if it's over the level, otherwise right where it was copied from
+    let cursor_position = **world.resource::<CursorPosition>();
+
+    let offset = cursor_position
+        .zip(fragment_center(&scene))
+        .map_or(Vec2::ZERO, |(cursor, center)| cursor - center);
+
+    // References to things that weren't copied don't mean anything here
+    spawn_fragment(world, scene, EntityHashMap::default(), offset, "Paste");
+}
+
+fn fragment_center(scene: &DynamicScene) -> Option<Vec2> {
+    let positions: Vec<Vec2> = scene
+        .entities
+        .iter()
+        .flat_map(|entity| entity.components.iter())
+        // Pasted components might only be dynamic versions
+        .filter_map(|component| Transform::from_reflect(&**component))
+        .map(|transform| transform.translation.truncate())
+        .collect();
+
+    (!positions.is_empty()).then(|| positions.iter().sum::<Vec2>() / positions.len() as f32)
+}
+
+// Spawns the scene as new entities and makes them the selection
+fn spawn_fragment(
+    world: &mut World,
+    scene: DynamicScene,
+    mut entity_map: EntityHashMap<Entity>,
+    offset: Vec2,
+    label: &str,
+) {
+    if let Err(err) = scene.write_to_world(world, &mut entity_map) {
+        error!("Failed to spawn entities: {err}");
+        return;
+    }
+
+    let spawned: Vec<Entity> = scene
+        .entities
+        .iter()
+        .filter_map(|entity| entity_map.get(&entity.entity).copied())
+        .collect();
+
+    let previous: Vec<Entity> = world
+        .query_filtered::<Entity, With<Selected>>()
+        .iter(world)
+        .collect();
+
+    for entity in previous {
+        world.entity_mut(entity).remove::<Selected>();
+    }
+
+    for &entity in &spawned {
+        let mut entity = world.entity_mut(entity);
+
+        if let Some(mut transform) = entity.get_mut::<Transform>() {
+            transform.translation += offset.extend(0.0);
+        }
+
+        entity.insert(Selected);
+    }
+
+    info!("{label}: spawned {} entities", spawned.len());
+
+    world.resource_mut::<History>().record_spawn(label, spawned);
+}
@@ -0,0 +1,730 @@
+use std::any::TypeId;
+
+use bevy::{
+    ecs::entity::EntityHashMap,
+    prelude::*,
+    reflect::PartialReflect,
+    scene::{DynamicEntity, SceneFilter},
+};
+
+use crate::{
+    game::{gravity::GravitySettings, prediction::PredictionLimit},
+    serialization::{GameSerializable, StartPoint, editor::level_scene_builder},
+};
+
+// Oldest entries get dropped past this
+const MAX_HISTORY: usize = 100;
+
+// Edits with the same label this close together become one entry, so dragging a slider is one undo
+const COALESCE_SECONDS: f32 = 0.5;
+
+/// Undo/redo for the editor
+///
+/// Systems record what they did and the history works out how to reverse it at the end of the frame,
+/// everything in the level is snapshotted through reflection so any level component can be undone
+#[derive(Resource, Default)]
+pub struct History {
+    done: Vec<HistoryEntry>,
+    undone: Vec<HistoryEntry>,
+    // Recorded this frame, waiting on world access
+    queued: Vec<QueuedEdit>,
+    request: Option<HistoryRequest>,
+}
+
+struct HistoryEntry {
+    label: String,
+    command: EditCommand,
+    time: f32,
+}
+
+enum EditCommand {
+    // Undo despawns the entities, redo spawns them back from the snapshot
+    Spawn(EntitySnapshot),
+    // The other way around
+    Despawn(EntitySnapshot),
+    // Components get written over the live entities
+    Edit {
+        before: EntitySnapshot,
+        after: EntitySnapshot,
+    },
+    SetStartPoint {
+        before: Option<Vec2>,
+        after: Option<Vec2>,
+    },
+    SetGravitySettings {
+        before: GravitySettings,
+        after: GravitySettings,
+    },
+    SetPredictionLimit {
+        before: PredictionLimit,
+        after: PredictionLimit,
+    },
+}
+
+/// Some level entities captured through reflection
+pub struct EntitySnapshot {
+    scene: DynamicScene,
+    // Snapshot entity -> the entity it is in the world right now, respawning changes these
+    live: EntityHashMap<Entity>,
+    // Components that get removed on write if the snapshot doesn't have them, for markers
+    tracked: Vec<TypeId>,
+}
+
+impl EntitySnapshot {
+    /// Snapshots specific components, for systems that change things directly and know the old values
+    pub fn from_components(
+        components: impl IntoIterator<Item = (Entity, Vec<Box<dyn PartialReflect>>)>,
+    ) -> Self {
+        let entities: Vec<DynamicEntity> = components
+            .into_iter()
+            .map(|(entity, components)| DynamicEntity { entity, components })
+            .collect();
+
+        EntitySnapshot {
+            live: entities
+                .iter()
+                .map(|entity| (entity.entity, entity.entity))
+                .collect(),
+            scene: DynamicScene {
+                resources: vec![],
+                entities,
+            },
+            tracked: vec![],
+        }
+    }
+
+    /// Lets an edit add or remove `T`, its absence from the snapshot gets restored too
+    pub fn tracking<T: Component>(mut self) -> Self {
+        self.tracked.push(TypeId::of::<T>());
+        self
+    }
+
+    fn capture(world: &World, entities: &[Entity], filter: Option<SceneFilter>) -> Self {
+        let mut builder = level_scene_builder(world);
+
+        if let Some(filter) = filter {
+            builder = builder.with_component_filter(filter);
+        }
+
+        let scene = builder.extract_entities(entities.iter().copied()).build();
+
+        EntitySnapshot {
+            live: entities.iter().map(|&entity| (entity, entity)).collect(),
+            scene,
+            tracked: vec![],
+        }
+    }
+
+    // Only the components that are in this snapshot
+    fn filter(&self) -> SceneFilter {
+        self.scene
+            .entities
+            .iter()
+            .flat_map(|entity| entity.components.iter())
+            .filter_map(|component| component.get_represented_type_info())
+            .map(|info| info.type_id())
+            .chain(self.tracked.iter().copied())
+            .fold(SceneFilter::deny_all(), |filter, type_id| {
+                filter.allow_by_id(type_id)
+            })
+    }
+
+    fn live_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.live.values().copied()
+    }
+
+    fn despawn(&self, world: &mut World) {
+        for entity in self.live_entities() {
+            if let Ok(entity) = world.get_entity_mut(entity) {
+                entity.despawn();
+            }
+        }
+    }
+
+    /// Spawns the snapshot as new entities, returns (old live entity, new entity) for remapping
+    fn respawn(&mut self, world: &mut World) -> Vec<(Entity, Entity)> {
+        let mut entity_map = outside_references(world, &self.scene);
+
+        if let Err(err) = self.scene.write_to_world(world, &mut entity_map) {
+            error!("Failed to respawn entities from history: {err}");
+        }
+
+        entity_map.retain(|snapshot, _| {
+            self.scene
+                .entities
+                .iter()
+                .any(|entity| entity.entity == *snapshot)
+        });
+
+        let remapped = self
+            .live
+            .iter()
+            .filter_map(|(snapshot, old)| Some((*old, *entity_map.get(snapshot)?)))
+            .collect();
+
+        self.live = entity_map;
+
+        remapped
+    }
+
+    /// Writes the snapshot's components over the live entities
+    fn write(&mut self, world: &mut World) {
+        // Entities that were despawned some other way are left out, writing to them would panic
+        let (entities, gone): (Vec<_>, Vec<_>) = std::mem::take(&mut self.scene.entities)
+            .into_iter()
+            .partition(|entity| {
+                self.live
+                    .get(&entity.entity)
+                    .is_some_and(|&live| world.get_entity(live).is_ok())
+            });
+
+        self.scene.entities = entities;
+
+        let mut entity_map = outside_references(world, &self.scene);
+        entity_map.extend(
+            self.live
+                .iter()
+                .filter(|(_, live)| world.get_entity(**live).is_ok())
+                .map(|(&snapshot, &live)| (snapshot, live)),
+        );
+
+        if let Err(err) = self.scene.write_to_world(world, &mut entity_map) {
+            error!("Failed to restore entities from history: {err}");
+        }
+
+        for snapshot_entity in &self.scene.entities {
+            let Some(&entity) = self.live.get(&snapshot_entity.entity) else {
+                continue;
+            };
+
+            let missing: Vec<_> = self
+                .tracked
+                .iter()
+                .filter(|&&type_id| {
+                    !snapshot_entity.components.iter().any(|component| {
+                        component
+                            .get_represented_type_info()
+                            .is_some_and(|info| info.type_id() == type_id)
+                    })
+                })
+                .filter_map(|&type_id| world.components().get_id(type_id))
+                .collect();
+
+            if let Ok(mut entity) = world.get_entity_mut(entity) {
+                for component_id in missing {
+                    entity.remove_by_id(component_id);
+                }
+            }
+        }
+
+        // Anything built from the serializable components has to be built again
+        for entity in self.live_entities() {
+            if let Ok(mut entity) = world.get_entity_mut(entity) {
+                entity.remove::<(
+                    Mesh2d,
+                    MeshMaterial2d<ColorMaterial>,
+                    avian2d::prelude::Collider,
+                )>();
+            }
+        }
+
+        self.scene.entities.extend(gone);
+    }
+
+    fn remap(&mut self, old: Entity, new: Entity) {
+        for live in self.live.values_mut() {
+            if *live == old {
+                *live = new;
+            }
+        }
+    }
+
+    fn same_entities(&self, other: &EntitySnapshot) -> bool {
+        self.live.len() == other.live.len()
+            && self
+                .live_entities()
+                .all(|entity| other.live_entities().any(|other| other == entity))
+    }
+}
+
+// Components can point at entities that aren't in the snapshot (like a rail's parent), those
+// have to map to themselves or the scene spawns empty entities for them
+pub(super) fn outside_references(world: &mut World, scene: &DynamicScene) -> EntityHashMap<Entity> {
+    world
+        .query_filtered::<Entity, With<GameSerializable>>()
+        .iter(world)
+        .filter(|entity| !scene.entities.iter().any(|inner| inner.entity == *entity))
+        .map(|entity| (entity, entity))
+        .collect()
+}
+
+enum QueuedEdit {
+    Spawned {
+        label: String,
+        entities: Vec<Entity>,
+    },
+    Despawn {
+        label: String,
+        entities: Vec<Entity>,
+    },
+    Edited {
+        label: String,
+        before: EntitySnapshot,
+    },
+    StartPoint {
+        before: Option<Vec2>,
+        after: Option<Vec2>,
+    },
+    GravitySettings {
+        before: GravitySettings,
+        after: GravitySettings,
+    },
+    PredictionLimit {
+        before: PredictionLimit,
+        after: PredictionLimit,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HistoryRequest {
+    Undo,
+    Redo,
+    // Undo or redo until this many entries are done
+    JumpTo(usize),
+}
+
+impl History {
+    /// For entities that were just spawned
+    pub fn record_spawn(
+        &mut self,
+        label: impl Into<String>,
+        entities: impl IntoIterator<Item = Entity>,
+    ) {
+        self.queued.push(QueuedEdit::Spawned {
+            label: label.into(),
+            entities: entities.into_iter().collect(),
+        });
+    }
+
+    /// Despawns the entities at the end of the frame, so they can be brought back
+    pub fn despawn(
+        &mut self,
+        label: impl Into<String>,
+        entities: impl IntoIterator<Item = Entity>,
+    ) {
+        self.queued.push(QueuedEdit::Despawn {
+            label: label.into(),
+            entities: entities.into_iter().collect(),
+        });
+    }
+
+    /// For components that were changed directly, `before` has their old values
+    ///
+    /// The new values are read at the end of the frame
+    pub fn record_edit(&mut self, label: impl Into<String>, before: EntitySnapshot) {
+        self.queued.push(QueuedEdit::Edited {
+            label: label.into(),
+            before,
+        });
+    }
+
+    pub fn record_start_point(&mut self, before: Option<Vec2>, after: Option<Vec2>) {
+        if before != after {
+            self.queued.push(QueuedEdit::StartPoint { before, after });
+        }
+    }
+
+    pub fn record_gravity_settings(&mut self, before: GravitySettings, after: GravitySettings) {
+        if before != after {
+            self.queued
+                .push(QueuedEdit::GravitySettings { before, after });
+        }
+    }
+
+    pub fn record_prediction_limit(&mut self, before: PredictionLimit, after: PredictionLimit) {
+        if before != after {
+            self.queued
+                .push(QueuedEdit::PredictionLimit { before, after });
+        }
+    }
+
+    pub fn request(&mut self, request: HistoryRequest) {
+        self.request = Some(request);
+    }
+
+    pub fn clear(&mut self) {
+        *self = History::default();
+    }
+
+    /// Labels of every entry oldest first, with whether it's currently done
+    pub fn entries(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.done
+            .iter()
+            .map(|entry| (entry.label.as_str(), true))
+            .chain(
+                self.undone
+                    .iter()
+                    .rev()
+                    .map(|entry| (entry.label.as_str(), false)),
+            )
+    }
+
+    pub fn done_count(&self) -> usize {
+        self.done.len()
+    }
+
+    fn push(&mut self, label: String, command: EditCommand, time: f32) {
+        self.undone.clear();
+
+        let last = self
+            .done
+            .last_mut()
+            .filter(|last| last.label == label && time - last.time < COALESCE_SECONDS);
+
+        let command = match last {
+            Some(last) => {
+                let Some(command) = last.command.coalesce(command) else {
+                    last.time = time;
+                    return;
+                };
+
+                command
+            }
+            None => command,
+        };
+
+        self.done.push(HistoryEntry {
+            label,
+            command,
+            time,
+        });
+
+        if self.done.len() > MAX_HISTORY {
+            self.done.remove(0);
+        }
+    }
+
+    fn remap(&mut self, remapped: &[(Entity, Entity)]) {
+        for entry in self.done.iter_mut().chain(self.undone.iter_mut()) {
+            let snapshots: Vec<&mut EntitySnapshot> = match &mut entry.command {
+                EditCommand::Spawn(snapshot) | EditCommand::Despawn(snapshot) => vec![snapshot],
+                EditCommand::Edit { before, after } => vec![before, after],
+                EditCommand::SetStartPoint { .. }
+                | EditCommand::SetGravitySettings { .. }
+                | EditCommand::SetPredictionLimit { .. } => vec![],
+            };
+
+            for snapshot in snapshots {
+                for &(old, new) in remapped {
+                    snapshot.remap(old, new);
+                }
+            }
+        }
+    }
+}
+
+impl EditCommand {
+    // Folds a newer command into this one, gives it back if they can't be merged
+    fn coalesce(&mut self, newer: EditCommand) -> Option<EditCommand> {
+        match (self, newer) {
+            (
+                EditCommand::Edit { before, after },
+                EditCommand::Edit {
+                    before: newer_before,
+                    after: newer_after,
+                },
+            ) if before.same_entities(&newer_before) => {
+                *after = newer_after;
+                None
+            }
+            (
+                EditCommand::SetStartPoint { after, .. },
+                EditCommand::SetStartPoint {
+                    after: newer_after, ..
+                },
+            ) => {
+                *after = newer_after;
+                None
+            }
+            (
+                EditCommand::SetGravitySettings { after, .. },
+                EditCommand::SetGravitySettings {
+                    after: newer_after, ..
+                },
+            ) => {
+                *after = newer_after;
+                None
+            }
+            (
+                EditCommand::SetPredictionLimit { after, .. },
+                EditCommand::SetPredictionLimit {
+                    after: newer_after, ..
+                },
+            ) => {
+                *after = newer_after;
+                None
+            }
+            (_, newer) => Some(newer),
+        }
+    }
+
+    fn undo(&mut self, world: &mut World) -> Vec<(Entity, Entity)> {
+        match self {
+            EditCommand::Spawn(snapshot) => {
+                snapshot.despawn(world);
+                vec![]
+            }
+            EditCommand::Despawn(snapshot) => snapshot.respawn(world),
+            EditCommand::Edit { before, .. } => {
+                before.write(world);
+                vec![]
+            }
+            EditCommand::SetStartPoint { before, .. } => {
+                **world.resource_mut::<StartPoint>() = *before;
+                vec![]
+            }
+            EditCommand::SetGravitySettings { before, .. } => {
+                *world.resource_mut::<GravitySettings>() = *before;
+                vec![]
+            }
+            EditCommand::SetPredictionLimit { before, .. } => {
+                *world.resource_mut::<PredictionLimit>() = *before;
+                vec![]
+            }
+        }
+    }
+
+    fn redo(&mut self, world: &mut World) -> Vec<(Entity, Entity)> {
+        match self {
+            EditCommand::Spawn(snapshot) => snapshot.respawn(world),
+            EditCommand::Despawn(snapshot) => {
+                snapshot.despawn(world);
+                vec![]
+            }
+            EditCommand::Edit { after, .. } => {
+                after.write(world);
+                vec![]
+            }
+            EditCommand::SetStartPoint { after, .. } => {
+                **world.resource_mut::<StartPoint>() = *after;
+                vec![]
+            }
+            EditCommand::SetGravitySettings { after, .. } => {
+                *world.resource_mut::<GravitySettings>() = *after;
+                vec![]
+            }
+            EditCommand::SetPredictionLimit { after, .. } => {
+                *world.resource_mut::<PredictionLimit>() = *after;
+                vec![]
+            }
+        }
+    }
+}
+
+// Exclusive since undoing can touch anything in the level
+pub fn apply_history(world: &mut World) {
+    world.resource_scope(|world, mut history: Mut<History>| {
+        let time = world.resource::<Time>().elapsed_secs();
+
+        for queued in std::mem::take(&mut history.queued) {
+            let (label, command) = match queued {
+                QueuedEdit::Spawned { label, entities } => (
+                    label,
+                    EditCommand::Spawn(EntitySnapshot::capture(world, &entities, None)),
+                ),
+                QueuedEdit::Despawn { label, entities } => {
+                    let snapshot = EntitySnapshot::capture(world, &entities, None);
+
+                    snapshot.despawn(world);
+
+                    (label, EditCommand::Despawn(snapshot))
+                }
+                QueuedEdit::Edited { label, before } => {
+                    let entities: Vec<Entity> = before.live_entities().collect();
+                    let mut after =
+                        EntitySnapshot::capture(world, &entities, Some(before.filter()));
+
+                    after.tracked = before.tracked.clone();
+
+                    (label, EditCommand::Edit { before, after })
+                }
+                QueuedEdit::StartPoint { before, after } => (
+                    "Move start point".to_string(),
+                    EditCommand::SetStartPoint { before, after },
+                ),
+                QueuedEdit::GravitySettings { before, after } => (
+                    "Change gravity".to_string(),
+                    EditCommand::SetGravitySettings { before, after },
+                ),
+                QueuedEdit::PredictionLimit { before, after } => (
+                    "Change prediction limit".to_string(),
+                    EditCommand::SetPredictionLimit { before, after },
+                ),
+            };
+
+            history.push(label, command, time);
+        }
+
+        let target = match history.request.take() {
+            Some(HistoryRequest::Undo) => history.done.len().saturating_sub(1),
+            Some(HistoryRequest::Redo) => history.done.len() + 1,
+            Some(HistoryRequest::JumpTo(target)) => target,
+            None => return,
+        };
+
+        while history.done.len() > target {
+            let Some(mut entry) = history.done.pop() else {
+                break;
+            };
+
+            info!("Undo: {}", entry.label);
+
+            let remapped = entry.command.undo(world);
+
+            history.undone.push(entry);
+            history.remap(&remapped);
+        }
+
+        while history.done.len() < target {
+            let Some(mut entry) = history.undone.pop() else {
+                break;
+            };
+
+            info!("Redo: {}", entry.label);
+
+            let remapped = entry.command.redo(world);
+
+            history.done.push(entry);
+            history.remap(&remapped);
+        }
+    });
+}
+
+pub fn history_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut history: ResMut<History>,
+    mut contexts: bevy_egui::EguiContexts,
+) {
+    // Text fields have their own undo
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+
+    let control = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if control && keys.just_pressed(KeyCode::KeyZ) {
+        if shift {
+            history.request(HistoryRequest::Redo);
+        } else {
+            history.request(HistoryRequest::Undo);
+        }
+    }
+}
+
+pub fn clear_history(mut history: ResMut<History>) {
+    history.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use avian2d::prelude::Mass;
+
+    // Time is never advanced on its own, so coalescing doesn't depend on how fast the test runs
+    fn history_app() -> App {
+        let mut app = App::new();
+
+        app.add_plugins(crate::serialization::SerializeableTypeRegistrationPlugin)
+            .init_resource::<Time>()
+            .init_resource::<History>()
+            .add_systems(Update, apply_history);
+
+        app
+    }
+
+    fn set_mass(app: &mut App, entity: Entity, mass: f32) {
+        let before = *app.world().get::<Mass>(entity).unwrap();
+
+        app.world_mut().resource_mut::<History>().record_edit(
+            "Change mass",
+            EntitySnapshot::from_components([(entity, vec![Box::new(before) as _])]),
+        );
+        app.world_mut().get_mut::<Mass>(entity).unwrap().0 = mass;
+        app.update();
+    }
+
+    fn request(app: &mut App, request: HistoryRequest) {
+        app.world_mut().resource_mut::<History>().request(request);
+        app.update();
+    }
+
+    fn mass(app: &App, entity: Entity) -> f32 {
+        app.world().get::<Mass>(entity).unwrap().0
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut app = history_app();
+        let entity = app.world_mut().spawn((GameSerializable, Mass(1.0))).id();
+
+        set_mass(&mut app, entity, 2.0);
+
+        request(&mut app, HistoryRequest::Undo);
+        assert_eq!(mass(&app, entity), 1.0);
+
+        request(&mut app, HistoryRequest::Redo);
+        assert_eq!(mass(&app, entity), 2.0);
+
+        request(&mut app, HistoryRequest::JumpTo(0));
+        assert_eq!(mass(&app, entity), 1.0);
+        assert_eq!(app.world().resource::<History>().done_count(), 0);
+    }
+
+    #[test]
+    fn quick_edits_coalesce() {
+        let mut app = history_app();
+        let entity = app.world_mut().spawn((GameSerializable, Mass(1.0))).id();
+
+        set_mass(&mut app, entity, 2.0);
+        set_mass(&mut app, entity, 3.0);
+
+        assert_eq!(app.world().resource::<History>().done_count(), 1);
+
+        // Slower ones are their own entry
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(std::time::Duration::from_secs_f32(COALESCE_SECONDS * 2.0));
+        set_mass(&mut app, entity, 4.0);
+
+        assert_eq!(app.world().resource::<History>().done_count(), 2);
+
+        request(&mut app, HistoryRequest::Undo);
+        assert_eq!(mass(&app, entity), 3.0);
+
+        request(&mut app, HistoryRequest::Undo);
+        assert_eq!(mass(&app, entity), 1.0);
+    }
+
+    #[test]
+    fn oldest_entries_get_dropped() {
+        let mut history = History::default();
+
+        for i in 0..MAX_HISTORY + 10 {
+            history.push(
+                format!("Move start point {i}"),
+                EditCommand::SetStartPoint {
+                    before: None,
+                    after: Some(Vec2::splat(i as f32)),
+                },
+                0.0,
+            );
+        }
+
+        assert_eq!(history.done_count(), MAX_HISTORY);
+        assert_eq!(
+            history.entries().next(),
+            Some(("Move start point 10", true))
+        );
+    }
+}
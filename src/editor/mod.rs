@@ -1,4 +1,12 @@
+mod browser;
+mod clipboard;
+pub mod history;
+mod properties;
+pub mod selection;
+pub mod snapping;
+mod toasts;
 mod ui;
+mod zone_editing;
 
 use avian2d::prelude::*;
 use bevy::prelude::*;
@@ -90,10 +98,54 @@ impl Plugin for EditorPlugin {
             });
         }
 
-        app.add_systems(
-            Update,
-            (editor_input_handler, ui::side_menu).run_if(app_state_is(crate::AppState::Editor)),
-        );
+        app.init_resource::<browser::LevelBrowser>()
+            .init_resource::<toasts::Toasts>()
+            .init_resource::<history::History>()
+            .init_resource::<selection::SelectionDrag>()
+            .init_resource::<clipboard::EditorClipboard>()
+            .init_resource::<snapping::SnapSettings>()
+            .init_resource::<zone_editing::ZoneEditing>()
+            .add_systems(
+                Update,
+                (
+                    toasts::collect_toasts,
+                    browser::forget_backups.run_if(
+                        on_event::<crate::serialization::editor::SaveResult>
+                            .or(on_event::<crate::serialization::editor::LoadResult>),
+                    ),
+                    // Entity ids in the history don't survive the level being replaced
+                    history::clear_history.run_if(
+                        on_event::<crate::serialization::LoadEvent>
+                            .or(resource_changed::<crate::AppState>),
+                    ),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    editor_input_handler.run_if(zone_editing::zone_editing_inactive),
+                    ui::side_menu,
+                    toasts::draw_toasts,
+                    history::history_input,
+                    (selection::selection_input, selection::drag_selection)
+                        .chain()
+                        .run_if(zone_editing::zone_editing_inactive),
+                    selection::draw_selection_gizmos,
+                    clipboard::clipboard_input.run_if(zone_editing::zone_editing_inactive),
+                    snapping::draw_snap_gizmos,
+                    zone_editing::zone_editing_input,
+                    zone_editing::draw_zone_editing_gizmos,
+                )
+                    .run_if(app_state_is(crate::AppState::Editor)),
+            )
+            // After everything in update so all of this frame's edits have been applied
+            .add_systems(
+                PostUpdate,
+                (clipboard::apply_clipboard, history::apply_history)
+                    .chain()
+                    .before(bevy::transform::TransformSystem::TransformPropagate)
+                    .run_if(app_state_is(crate::AppState::Editor)),
+            );
     }
 }
 
@@ -102,29 +154,46 @@ pub fn editor_input_handler(
     mouse: Res<ButtonInput<MouseButton>>,
     cursor_position: Res<crate::cursor::CursorPosition>,
     mut starting_position: ResMut<crate::StartPoint>,
+    mut history: ResMut<history::History>,
+    snapping: snapping::Snapping,
     mut commands: Commands,
 ) {
     if let Some(cursor_position) = **cursor_position {
+        let cursor_position = snapping.point(cursor_position);
+
         if keys.pressed(KeyCode::ShiftLeft) {
             // Stuff will go here!
         } else {
             if mouse.just_pressed(MouseButton::Right) {
-                commands.spawn(
-                    LevelObjectBundle::from_circle(Circle::new(10.0))
-                        .with_position(cursor_position),
-                );
+                let object = commands
+                    .spawn(
+                        LevelObjectBundle::from_circle(Circle::new(10.0))
+                            .with_position(cursor_position),
+                    )
+                    .id();
+
+                history.record_spawn("Add object", [object]);
             }
 
             if mouse.just_pressed(MouseButton::Middle) {
+                history.record_start_point(**starting_position, Some(cursor_position));
+
                 **starting_position = Some(cursor_position)
             }
 
-            if keys.just_pressed(KeyCode::KeyZ) {
+            // Ctrl+Z is undo
+            if keys.just_pressed(KeyCode::KeyZ)
+                && !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+            {
                 // I'll add a bundle for this later
-                commands.spawn(
-                    crate::game::trigger::GameTriggerBundle::default()
-                        .with_position(cursor_position),
-                );
+                let trigger = commands
+                    .spawn(
+                        crate::game::trigger::GameTriggerBundle::default()
+                            .with_position(cursor_position),
+                    )
+                    .id();
+
+                history.record_spawn("Add trigger", [trigger]);
             }
         }
     }
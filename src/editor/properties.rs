@@ -0,0 +1,760 @@
+use avian2d::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*, reflect::PartialReflect};
+use bevy_egui::egui;
+
+use super::{
+    history::{EntitySnapshot, History},
+    selection::Selected,
+};
+use crate::{
+    game::{
+        collision::CollisionResponse,
+        death::KillOnCollision,
+        gravity::{
+            GravityLayer, GravityLayers,
+            sources::{GravityShape, GravitySource},
+        },
+        rails::{OrbitRail, RailPath},
+    },
+    serialization::{
+        GameSerializable, colliders::SerializableCollider, materials::SerilializableMeshMaterial,
+        meshes::SerializableMesh,
+    },
+};
+
+// Where a body's rail goes around when it's first put on one
+const DEFAULT_RAIL_RADIUS: f32 = 100.0;
+
+type SelectedComponents = (
+    Entity,
+    Option<&'static mut Mass>,
+    Option<&'static mut SerializableMesh>,
+    Option<&'static mut SerializableCollider>,
+    Option<&'static mut SerilializableMeshMaterial>,
+    Option<&'static mut RigidBody>,
+    Option<&'static mut LinearVelocity>,
+    Option<&'static mut GravityLayers>,
+    Option<&'static mut OrbitRail>,
+    Option<&'static Transform>,
+    Option<&'static mut CollisionResponse>,
+    Option<&'static mut GravitySource>,
+    Has<KillOnCollision>,
+    Option<&'static BodyBeforeRail>,
+);
+
+// Anything with mass can be something else's rail parent
+type RailParents<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Mass,
+        &'static Transform,
+        Option<&'static OrbitRail>,
+    ),
+    (With<GameSerializable>, Without<Selected>),
+>;
+
+/// What a body was before it went on a rail, so taking it off puts it back
+#[derive(Component, Clone, Copy)]
+pub struct BodyBeforeRail(RigidBody);
+
+/// Everything the property panel can edit on the selected entity
+#[derive(SystemParam)]
+pub struct SelectedProperties<'w, 's> {
+    selected: Query<'w, 's, SelectedComponents, With<Selected>>,
+    parents: RailParents<'w, 's>,
+    commands: Commands<'w, 's>,
+}
+
+const GRAVITY_LAYERS: [(GravityLayer, &str); 2] =
+    [(GravityLayer::Main, "Main"), (GravityLayer::Level, "Level")];
+
+fn boxed(component: impl PartialReflect) -> Box<dyn PartialReflect> {
+    Box::new(component)
+}
+
+/// Shows the selected entity's components, every change goes through the history
+pub fn properties_ui(
+    ui: &mut egui::Ui,
+    properties: &mut SelectedProperties,
+    history: &mut History,
+) {
+    let count = properties.selected.iter().count();
+
+    if count != 1 {
+        ui.label(match count {
+            0 => "Nothing selected".to_string(),
+            count => format!("{count} entities selected"),
+        });
+        return;
+    }
+
+    let Ok((
+        entity,
+        mass,
+        mesh,
+        collider,
+        material,
+        rigid_body,
+        velocity,
+        gravity_layers,
+        rail,
+        transform,
+        collision_response,
+        gravity_source,
+        kill_on_collision,
+        body_before_rail,
+    )) = properties.selected.single_mut()
+    else {
+        return;
+    };
+
+    // Copied before the body section gets to it, putting something on a rail changes it
+    let body = rigid_body.as_deref().copied();
+
+    // A new gravity source starts out pulling as hard as the body already did
+    let mass_value = mass.as_deref().map_or(0.0, |mass| mass.0);
+
+    if let Some(mut mass) = mass {
+        let mut value = mass.0;
+
+        ui.horizontal(|ui| {
+            ui.label("Mass");
+            ui.add(
+                egui::DragValue::new(&mut value)
+                    .speed(0.1)
+                    .range(0.0..=f32::MAX),
+            );
+        });
+
+        if value != mass.0 {
+            history.record_edit(
+                "Change mass",
+                EntitySnapshot::from_components([(entity, vec![boxed(*mass)])]),
+            );
+            mass.0 = value;
+        }
+    }
+
+    // Circles only, the mesh and collider are kept the same size
+    if let (Some(mut mesh), Some(mut collider)) = (mesh, collider)
+        && let Some(radius) = mesh.circle_radius()
+    {
+        let mut value = radius;
+
+        ui.horizontal(|ui| {
+            ui.label("Radius");
+            ui.add(
+                egui::DragValue::new(&mut value)
+                    .speed(0.1)
+                    .range(0.1..=f32::MAX),
+            );
+        });
+
+        if value != radius {
+            history.record_edit(
+                "Change radius",
+                EntitySnapshot::from_components([(
+                    entity,
+                    vec![boxed(mesh.clone()), boxed(collider.clone())],
+                )]),
+            );
+
+            *mesh = SerializableMesh::primitive(Circle::new(value));
+            *collider = SerializableCollider::from(Circle::new(value));
+
+            // Both get rebuilt from the serializable versions
+            properties
+                .commands
+                .entity(entity)
+                .remove::<(Mesh2d, Collider)>();
+        }
+    }
+
+    if let Some(mut material) = material {
+        let SerilializableMeshMaterial::Color(color_material) = material.as_ref();
+        let mut color = color_material.color.to_srgba().to_f32_array();
+
+        ui.horizontal(|ui| {
+            ui.label("Color");
+            ui.color_edit_button_rgba_unmultiplied(&mut color);
+        });
+
+        if color != color_material.color.to_srgba().to_f32_array() {
+            history.record_edit(
+                "Change color",
+                EntitySnapshot::from_components([(entity, vec![boxed(material.clone())])]),
+            );
+
+            let SerilializableMeshMaterial::Color(color_material) = material.as_mut();
+            color_material.color = Color::srgba(color[0], color[1], color[2], color[3]);
+
+            properties
+                .commands
+                .entity(entity)
+                .remove::<MeshMaterial2d<ColorMaterial>>();
+        }
+    }
+
+    if let Some(mut rigid_body) = rigid_body {
+        let mut value = *rigid_body;
+
+        ui.horizontal(|ui| {
+            ui.label("Body");
+            ui.radio_value(&mut value, RigidBody::Static, "Static");
+            ui.radio_value(&mut value, RigidBody::Dynamic, "Dynamic");
+            ui.radio_value(&mut value, RigidBody::Kinematic, "Kinematic");
+        });
+
+        if value != *rigid_body {
+            history.record_edit(
+                "Change body",
+                EntitySnapshot::from_components([(entity, vec![boxed(*rigid_body)])]),
+            );
+            *rigid_body = value;
+        }
+    }
+
+    if let Some(mut velocity) = velocity {
+        let mut value = velocity.0;
+
+        ui.horizontal(|ui| {
+            ui.label("Velocity");
+            ui.add(egui::DragValue::new(&mut value.x).speed(1.0).prefix("x: "));
+            ui.add(egui::DragValue::new(&mut value.y).speed(1.0).prefix("y: "));
+        });
+
+        if value != velocity.0 {
+            history.record_edit(
+                "Change velocity",
+                EntitySnapshot::from_components([(entity, vec![boxed(*velocity)])]),
+            );
+            velocity.0 = value;
+        }
+    }
+
+    if let Some(mut gravity_layers) = gravity_layers {
+        let mut value = *gravity_layers;
+
+        ui.label("Gravity layers");
+        egui::Grid::new("Gravity Layers").show(ui, |ui| {
+            ui.label("");
+            ui.label("Member");
+            ui.label("Pulled by");
+            ui.end_row();
+
+            for (layer, name) in GRAVITY_LAYERS {
+                let bits = layer.to_bits();
+
+                ui.label(name);
+
+                for mask in [&mut value.memberships, &mut value.filters] {
+                    let mut enabled = mask.0 & bits != 0;
+
+                    if ui.checkbox(&mut enabled, "").changed() {
+                        mask.0 ^= bits;
+                    }
+                }
+
+                ui.end_row();
+            }
+        });
+
+        if value != *gravity_layers {
+            history.record_edit(
+                "Change gravity layers",
+                EntitySnapshot::from_components([(entity, vec![boxed(*gravity_layers)])]),
+            );
+            *gravity_layers = value;
+        }
+    }
+
+    // Anything that takes part in physics can have a response, no component means solid
+    if body.is_some() {
+        let current = collision_response.as_deref().copied().unwrap_or_default();
+        let mut value = current;
+
+        ui.horizontal(|ui| {
+            ui.label("On collision");
+            ui.radio_value(&mut value, CollisionResponse::Solid, "Solid");
+            ui.radio_value(&mut value, CollisionResponse::Merge, "Merge");
+
+            let is_fragment = matches!(value, CollisionResponse::Fragment { .. });
+
+            if ui.radio(is_fragment, "Fragment").clicked() && !is_fragment {
+                value = CollisionResponse::fragment();
+            }
+        });
+
+        if let CollisionResponse::Fragment { pieces, min_speed } = &mut value {
+            ui.horizontal(|ui| {
+                ui.label("Pieces");
+                ui.add(egui::DragValue::new(pieces).range(2..=16));
+                ui.label("Above speed");
+                ui.add(
+                    egui::DragValue::new(min_speed)
+                        .speed(1.0)
+                        .range(0.0..=f32::MAX),
+                );
+            });
+        }
+
+        if value != current {
+            history.record_edit(
+                "Change collision response",
+                EntitySnapshot::from_components([(
+                    entity,
+                    collision_response
+                        .as_deref()
+                        .copied()
+                        .map(boxed)
+                        .into_iter()
+                        .collect(),
+                )])
+                .tracking::<CollisionResponse>(),
+            );
+
+            match collision_response {
+                Some(mut collision_response) => *collision_response = value,
+                None => {
+                    properties.commands.entity(entity).insert(value);
+                }
+            }
+        }
+    }
+
+    gravity_source_ui(
+        ui,
+        entity,
+        gravity_source,
+        mass_value,
+        &mut properties.commands,
+        history,
+    );
+
+    let mut kills = kill_on_collision;
+
+    ui.checkbox(&mut kills, "Kills on collision");
+
+    if kills != kill_on_collision {
+        let before = if kill_on_collision {
+            vec![boxed(KillOnCollision)]
+        } else {
+            vec![]
+        };
+
+        history.record_edit(
+            "Toggle kill on collision",
+            EntitySnapshot::from_components([(entity, before)]).tracking::<KillOnCollision>(),
+        );
+
+        if kills {
+            properties.commands.entity(entity).insert(KillOnCollision);
+        } else {
+            properties
+                .commands
+                .entity(entity)
+                .remove::<KillOnCollision>();
+        }
+    }
+
+    rail_ui(
+        ui,
+        entity,
+        rail,
+        transform,
+        body,
+        body_before_rail.copied(),
+        &properties.parents,
+        &mut properties.commands,
+        history,
+    );
+}
+
+// Whether `body` is on a rail around `ancestor`, even through other rails
+fn rides_on(body: Entity, ancestor: Entity, parents: &RailParents) -> bool {
+    let mut current = body;
+
+    // Bounded in case a level already has a loop in it
+    for _ in 0..=parents.iter().count() {
+        if current == ancestor {
+            return true;
+        }
+
+        match parents
+            .get(current)
+            .ok()
+            .and_then(|(.., rail)| rail?.parent)
+        {
+            Some(parent) => current = parent,
+            None => return false,
+        }
+    }
+
+    false
+}
+
+// Angles are edited in degrees, only converted back when they move
+fn angle_row(
+    ui: &mut egui::Ui,
+    label: &str,
+    radians: &mut f32,
+    range: std::ops::RangeInclusive<f32>,
+) {
+    let mut degrees = radians.to_degrees();
+
+    ui.label(label);
+    ui.add(
+        egui::DragValue::new(&mut degrees)
+            .speed(1.0)
+            .range(range)
+            .suffix("°"),
+    );
+    ui.end_row();
+
+    if degrees != radians.to_degrees() {
+        *radians = degrees.to_radians();
+    }
+}
+
+fn gravity_source_ui(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    source: Option<Mut<GravitySource>>,
+    mass: f32,
+    commands: &mut Commands,
+    history: &mut History,
+) {
+    let has_source = source.is_some();
+    let mut enabled = has_source;
+
+    ui.checkbox(&mut enabled, "Custom gravity source");
+
+    if enabled != has_source {
+        history.record_edit(
+            "Toggle gravity source",
+            EntitySnapshot::from_components([(
+                entity,
+                source.as_deref().copied().map(boxed).into_iter().collect(),
+            )])
+            .tracking::<GravitySource>(),
+        );
+
+        if enabled {
+            commands.entity(entity).insert(GravitySource {
+                strength: mass,
+                ..default()
+            });
+        } else {
+            commands.entity(entity).remove::<GravitySource>();
+        }
+
+        return;
+    }
+
+    let Some(mut source) = source else {
+        return;
+    };
+
+    let mut value = *source;
+
+    ui.horizontal(|ui| {
+        ui.label("Strength");
+        ui.add(egui::DragValue::new(&mut value.strength).speed(0.1));
+    });
+
+    if value.strength < 0.0 {
+        ui.label("Pushes things away");
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Shape");
+
+        let (is_cone, is_uniform) = (
+            matches!(value.shape, GravityShape::Cone { .. }),
+            matches!(value.shape, GravityShape::Uniform { .. }),
+        );
+
+        ui.radio_value(&mut value.shape, GravityShape::Point, "Point");
+
+        if ui.radio(is_cone, "Cone").clicked() && !is_cone {
+            value.shape = GravityShape::Cone {
+                direction: 0.0,
+                half_angle: 30.0_f32.to_radians(),
+                softness: 10.0_f32.to_radians(),
+            };
+        }
+
+        if ui.radio(is_uniform, "Uniform field").clicked() && !is_uniform {
+            value.shape = GravityShape::Uniform {
+                direction: -90.0_f32.to_radians(),
+            };
+        }
+    });
+
+    match &mut value.shape {
+        GravityShape::Point => {}
+        GravityShape::Cone {
+            direction,
+            half_angle,
+            softness,
+        } => {
+            egui::Grid::new("Gravity cone").show(ui, |ui| {
+                angle_row(ui, "Direction", direction, -180.0..=180.0);
+                angle_row(ui, "Half angle", half_angle, 0.0..=180.0);
+                angle_row(ui, "Softness", softness, 0.0..=180.0);
+            });
+        }
+        GravityShape::Uniform { direction } => {
+            egui::Grid::new("Gravity field").show(ui, |ui| {
+                angle_row(ui, "Direction", direction, -180.0..=180.0);
+            });
+
+            ui.label("Pulls on everything inside the mesh");
+        }
+    }
+
+    if value != *source {
+        history.record_edit(
+            "Change gravity source",
+            EntitySnapshot::from_components([(entity, vec![boxed(*source)])]),
+        );
+        *source = value;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rail_ui(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    rail: Option<Mut<OrbitRail>>,
+    transform: Option<&Transform>,
+    body: Option<RigidBody>,
+    body_before_rail: Option<BodyBeforeRail>,
+    parents: &RailParents,
+    commands: &mut Commands,
+    history: &mut History,
+) {
+    // Only bodies that don't move on their own can go on a rail
+    let Some(body) = body.filter(|body| *body != RigidBody::Dynamic || rail.is_some()) else {
+        return;
+    };
+
+    let on_rail = rail.is_some();
+    let mut enabled = on_rail;
+
+    ui.checkbox(&mut enabled, "On a rail");
+
+    if enabled != on_rail {
+        let mut before = vec![boxed(body)];
+        before.extend(rail.as_deref().cloned().map(boxed));
+
+        history.record_edit(
+            "Toggle rail",
+            EntitySnapshot::from_components([(entity, before)]).tracking::<OrbitRail>(),
+        );
+
+        if enabled {
+            let position =
+                transform.map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+
+            // Kinematic so collisions push things out of the way instead of stopping it
+            commands.entity(entity).insert((
+                OrbitRail::circular_from(position, DEFAULT_RAIL_RADIUS),
+                RigidBody::Kinematic,
+                BodyBeforeRail(body),
+            ));
+        } else {
+            // Levels don't save what it was, so loaded ones go back to static.
+            // It's left on so undoing this still knows what to go back to
+            let previous = body_before_rail.map_or(RigidBody::Static, |before| before.0);
+
+            commands
+                .entity(entity)
+                .remove::<OrbitRail>()
+                .insert(previous);
+        }
+
+        return;
+    }
+
+    let Some(mut rail) = rail else {
+        return;
+    };
+
+    let mut value = rail.clone();
+
+    ui.horizontal(|ui| {
+        ui.label("Path");
+
+        let is_kepler = matches!(value.path, RailPath::Kepler { .. });
+
+        // Switching keeps the path roughly the same size
+        if ui.radio(is_kepler, "Orbit").clicked() && !is_kepler {
+            let points = match &value.path {
+                RailPath::Spline { points } => points.clone(),
+                RailPath::Kepler { .. } => vec![],
+            };
+
+            let radius = if points.is_empty() {
+                DEFAULT_RAIL_RADIUS
+            } else {
+                points.iter().map(|point| point.length()).sum::<f32>() / points.len() as f32
+            };
+
+            value.path = RailPath::Kepler {
+                semi_major_axis: radius,
+                eccentricity: 0.0,
+                argument_of_periapsis: 0.0,
+                clockwise: false,
+            };
+        }
+
+        if ui.radio(!is_kepler, "Spline").clicked() && is_kepler {
+            value.path = RailPath::Spline {
+                points: (0..4)
+                    .map(|i| value.path.offset_at(i as f32 / 4.0))
+                    .collect(),
+            };
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Period");
+        ui.add(
+            egui::DragValue::new(&mut value.period)
+                .speed(0.1)
+                .range(0.1..=f32::MAX)
+                .suffix("s"),
+        );
+        ui.label("Phase");
+        ui.add(
+            egui::DragValue::new(&mut value.phase)
+                .speed(0.01)
+                .range(0.0..=1.0),
+        );
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Parent");
+
+        // Nothing has a name, so bodies go by their mass and where they are
+        let name = |parent: Option<Entity>| match parent.map(|parent| parents.get(parent)) {
+            Some(Ok((_, mass, transform, _))) => format!(
+                "Mass {:.1} at ({:.0}, {:.0})",
+                mass.0, transform.translation.x, transform.translation.y
+            ),
+            Some(Err(_)) => "Missing".to_string(),
+            None => "None".to_string(),
+        };
+
+        egui::ComboBox::from_id_salt("Rail parent")
+            .selected_text(name(value.parent))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut value.parent, None, name(None));
+
+                // Anything riding on this body can't be its parent too, it'd go round in circles
+                for (parent, ..) in parents
+                    .iter()
+                    .filter(|(parent, ..)| !rides_on(*parent, entity, parents))
+                {
+                    ui.selectable_value(&mut value.parent, Some(parent), name(Some(parent)));
+                }
+            });
+    });
+
+    // The anchor only matters without a parent
+    if value.parent.is_none() {
+        ui.horizontal(|ui| {
+            ui.label("Anchor");
+            ui.add(
+                egui::DragValue::new(&mut value.anchor.x)
+                    .speed(1.0)
+                    .prefix("x: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut value.anchor.y)
+                    .speed(1.0)
+                    .prefix("y: "),
+            );
+        });
+    }
+
+    match &mut value.path {
+        RailPath::Kepler {
+            semi_major_axis,
+            eccentricity,
+            argument_of_periapsis,
+            clockwise,
+        } => {
+            let mut degrees = argument_of_periapsis.to_degrees();
+
+            egui::Grid::new("Rail orbit").show(ui, |ui| {
+                ui.label("Semi-major axis");
+                ui.add(
+                    egui::DragValue::new(semi_major_axis)
+                        .speed(1.0)
+                        .range(1.0..=f32::MAX),
+                );
+                ui.end_row();
+
+                ui.label("Eccentricity");
+                ui.add(
+                    egui::DragValue::new(eccentricity)
+                        .speed(0.01)
+                        .range(0.0..=0.99),
+                );
+                ui.end_row();
+
+                ui.label("Periapsis angle");
+                ui.add(
+                    egui::DragValue::new(&mut degrees)
+                        .speed(1.0)
+                        .range(-180.0..=180.0)
+                        .suffix("°"),
+                );
+                ui.end_row();
+            });
+
+            ui.checkbox(clockwise, "Clockwise");
+
+            // Only converted back when it moves, otherwise the round trip counts as a change
+            if degrees != argument_of_periapsis.to_degrees() {
+                *argument_of_periapsis = degrees.to_radians();
+            }
+        }
+        RailPath::Spline { points } => {
+            let mut removed = None;
+
+            for (index, point) in points.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut point.x).speed(1.0).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut point.y).speed(1.0).prefix("y: "));
+
+                    if ui.small_button("x").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+
+            // A loop needs at least two points to go anywhere
+            if let Some(index) = removed.filter(|_| points.len() > 2) {
+                points.remove(index);
+            }
+
+            if ui.button("Add point").clicked() {
+                let last = points.last().copied().unwrap_or_default();
+                points.push(last + Vec2::X * DEFAULT_RAIL_RADIUS / 2.0);
+            }
+        }
+    }
+
+    if value != *rail {
+        history.record_edit(
+            "Change rail",
+            EntitySnapshot::from_components([(entity, vec![boxed(rail.clone())])]),
+        );
+        *rail = value;
+    }
+}
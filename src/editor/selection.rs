@@ -0,0 +1,412 @@
+use avian2d::prelude::*;
+use bevy::{prelude::*, reflect::PartialReflect};
+use bevy_egui::EguiContexts;
+
+use super::{
+    history::{EntitySnapshot, History},
+    snapping::Snapping,
+};
+use crate::{camera::GameCamera, cursor::CursorPosition, serialization::GameSerializable};
+
+// Handle sizes are in screen pixels, they get multiplied by the camera zoom
+const HANDLE_SIZE: f32 = 6.0;
+const HANDLE_OFFSET: f32 = 20.0;
+
+const SELECTION_COLOR: Color = Color::srgb(1.0, 0.8, 0.2);
+
+/// Marks level entities picked in the editor
+#[derive(Component)]
+pub struct Selected;
+
+/// What the left mouse button is doing right now, if anything
+#[derive(Resource, Default)]
+pub struct SelectionDrag(Option<DragKind>);
+
+enum DragKind {
+    // Transforms are from when the drag started, so the history gets the old ones
+    Move {
+        start: Vec2,
+        // Where the grabbed object started, it's what gets snapped
+        anchor: Vec2,
+        transforms: Vec<(Entity, Transform)>,
+    },
+    Rotate {
+        pivot: Vec2,
+        start: Vec2,
+        transforms: Vec<(Entity, Transform)>,
+    },
+    Scale {
+        pivot: Vec2,
+        start: Vec2,
+        transforms: Vec<(Entity, Transform)>,
+    },
+    Box {
+        start: Vec2,
+    },
+}
+
+struct SelectionHandles {
+    pivot: Vec2,
+    rotate: Vec2,
+    scale: Vec2,
+    // How close the cursor has to be to grab one
+    radius: f32,
+}
+
+impl SelectionHandles {
+    fn new(bounds: Rect, zoom: f32) -> Self {
+        let offset = HANDLE_OFFSET * zoom;
+
+        SelectionHandles {
+            pivot: bounds.center(),
+            rotate: Vec2::new(bounds.center().x, bounds.max.y + offset),
+            scale: bounds.max + Vec2::splat(offset * std::f32::consts::FRAC_1_SQRT_2),
+            radius: HANDLE_SIZE * zoom,
+        }
+    }
+}
+
+pub(super) fn camera_zoom(projection: &Projection) -> f32 {
+    match projection {
+        Projection::Orthographic(projection) => projection.scale,
+        _ => 1.0,
+    }
+}
+
+// Uses the collider's bounds where there is one, otherwise just the position
+fn entity_bounds(transform: &Transform, aabb: Option<&ColliderAabb>) -> Rect {
+    aabb.map_or(
+        Rect::from_center_size(transform.translation.truncate(), Vec2::ZERO),
+        |aabb| Rect::from_corners(aabb.min, aabb.max),
+    )
+}
+
+fn selection_bounds(bounds: impl Iterator<Item = Rect>) -> Option<Rect> {
+    bounds.reduce(|acc, bounds| acc.union(bounds))
+}
+
+type SelectableObjects<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Transform,
+        Option<&'static ColliderAabb>,
+        Has<Selected>,
+    ),
+    With<GameSerializable>,
+>;
+
+fn selected_transforms(objects: &SelectableObjects) -> Vec<(Entity, Transform)> {
+    objects
+        .iter()
+        .filter(|(.., selected)| *selected)
+        .map(|(entity, transform, ..)| (entity, *transform))
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn selection_input(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    cursor_position: Res<CursorPosition>,
+    mut contexts: EguiContexts,
+    spatial_query: SpatialQuery,
+    objects: SelectableObjects,
+    projection: Single<&Projection, With<GameCamera>>,
+    mut drag: ResMut<SelectionDrag>,
+    mut history: ResMut<History>,
+    mut commands: Commands,
+) {
+    // Let go outside of the viewport, whatever was moved still goes in the history
+    if mouse.just_released(MouseButton::Left)
+        && cursor_position.is_none()
+        && let Some(drag) = drag.0.take()
+    {
+        finish_transform_drag(drag, &objects, &mut history);
+    }
+
+    let Some(cursor_position) = **cursor_position else {
+        return;
+    };
+
+    // Shift pans the camera and control draws zones
+    let modified = keys.any_pressed([
+        KeyCode::ShiftLeft,
+        KeyCode::ShiftRight,
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+    ]);
+
+    // Alt adds to the selection instead of replacing it
+    let additive = keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+
+    if mouse.just_pressed(MouseButton::Left)
+        && !modified
+        && !contexts.ctx_mut().is_pointer_over_area()
+    {
+        let zoom = camera_zoom(&projection);
+
+        let handles = selection_bounds(
+            objects
+                .iter()
+                .filter(|(.., selected)| *selected)
+                .map(|(_, transform, aabb, _)| entity_bounds(transform, aabb)),
+        )
+        .map(|bounds| SelectionHandles::new(bounds, zoom));
+
+        let hit = spatial_query
+            .point_intersections(cursor_position, &SpatialQueryFilter::default())
+            .into_iter()
+            .find(|entity| objects.contains(*entity));
+
+        drag.0 = match (handles, hit) {
+            (Some(handles), _) if handles.rotate.distance(cursor_position) <= handles.radius => {
+                Some(DragKind::Rotate {
+                    pivot: handles.pivot,
+                    start: cursor_position,
+                    transforms: selected_transforms(&objects),
+                })
+            }
+            (Some(handles), _) if handles.scale.distance(cursor_position) <= handles.radius => {
+                Some(DragKind::Scale {
+                    pivot: handles.pivot,
+                    start: cursor_position,
+                    transforms: selected_transforms(&objects),
+                })
+            }
+            (_, Some(hit)) if additive => {
+                let is_selected = objects.get(hit).is_ok_and(|(.., selected)| selected);
+
+                if is_selected {
+                    commands.entity(hit).remove::<Selected>();
+                } else {
+                    commands.entity(hit).insert(Selected);
+                }
+
+                None
+            }
+            (_, Some(hit)) => {
+                let hit_selected = objects.get(hit).is_ok_and(|(.., selected)| selected);
+
+                // Clicking something outside the selection picks just that
+                let transforms = if hit_selected {
+                    selected_transforms(&objects)
+                } else {
+                    for (entity, ..) in objects.iter().filter(|(.., selected)| *selected) {
+                        commands.entity(entity).remove::<Selected>();
+                    }
+
+                    commands.entity(hit).insert(Selected);
+
+                    objects
+                        .get(hit)
+                        .map(|(entity, transform, ..)| vec![(entity, *transform)])
+                        .unwrap_or_default()
+                };
+
+                let anchor = objects
+                    .get(hit)
+                    .map_or(cursor_position, |(_, transform, ..)| {
+                        transform.translation.truncate()
+                    });
+
+                Some(DragKind::Move {
+                    start: cursor_position,
+                    anchor,
+                    transforms,
+                })
+            }
+            (_, None) => Some(DragKind::Box {
+                start: cursor_position,
+            }),
+        };
+    }
+
+    if mouse.just_released(MouseButton::Left) {
+        match drag.0.take() {
+            Some(DragKind::Box { start }) => {
+                let rect = Rect::from_corners(start, cursor_position);
+
+                for (entity, transform, _, selected) in &objects {
+                    let inside = rect.contains(transform.translation.truncate());
+
+                    if inside && !selected {
+                        commands.entity(entity).insert(Selected);
+                    } else if !inside && selected && !additive {
+                        commands.entity(entity).remove::<Selected>();
+                    }
+                }
+            }
+            Some(drag) => finish_transform_drag(drag, &objects, &mut history),
+            None => {}
+        }
+    }
+}
+
+// Puts a move, rotate or scale in the history if it changed anything
+fn finish_transform_drag(drag: DragKind, objects: &SelectableObjects, history: &mut History) {
+    let (label, transforms) = match drag {
+        DragKind::Move { transforms, .. } => ("Move", transforms),
+        DragKind::Rotate { transforms, .. } => ("Rotate", transforms),
+        DragKind::Scale { transforms, .. } => ("Scale", transforms),
+        DragKind::Box { .. } => return,
+    };
+
+    let moved = transforms.iter().any(|(entity, original)| {
+        objects
+            .get(*entity)
+            .is_ok_and(|(_, transform, ..)| transform != original)
+    });
+
+    if moved {
+        history.record_edit(
+            label,
+            EntitySnapshot::from_components(transforms.into_iter().map(|(entity, transform)| {
+                (entity, vec![Box::new(transform) as Box<dyn PartialReflect>])
+            })),
+        );
+    }
+}
+
+/// Moves, rotates or scales the selection while it's being dragged
+pub fn drag_selection(
+    mouse: Res<ButtonInput<MouseButton>>,
+    cursor_position: Res<CursorPosition>,
+    drag: Res<SelectionDrag>,
+    // Snapping looks at transforms too, so they take turns
+    mut params: ParamSet<(Query<&mut Transform, With<GameSerializable>>, Snapping)>,
+) {
+    let Some(cursor_position) = **cursor_position else {
+        return;
+    };
+
+    if !mouse.pressed(MouseButton::Left) {
+        return;
+    }
+
+    match &drag.0 {
+        Some(DragKind::Move {
+            start,
+            anchor,
+            transforms,
+        }) => {
+            let dragged: Vec<Entity> = transforms.iter().map(|(entity, _)| *entity).collect();
+
+            // The grabbed object snaps, not the cursor, and never onto anything being dragged
+            let delta = params
+                .p1()
+                .point_ignoring(*anchor + cursor_position - *start, &dragged)
+                - *anchor;
+
+            let mut objects = params.p0();
+
+            for (entity, original) in transforms {
+                if let Ok(mut transform) = objects.get_mut(*entity) {
+                    transform.translation = original.translation + delta.extend(0.0);
+                }
+            }
+        }
+        Some(DragKind::Rotate {
+            pivot,
+            start,
+            transforms,
+        }) => {
+            let (pivot, start) = (*pivot, *start);
+            let angle = (cursor_position - pivot).to_angle() - (start - pivot).to_angle();
+
+            let mut objects = params.p0();
+
+            for (entity, original) in transforms {
+                if let Ok(mut transform) = objects.get_mut(*entity) {
+                    let offset = original.translation.truncate() - pivot;
+
+                    transform.translation = (pivot + Vec2::from_angle(angle).rotate(offset))
+                        .extend(original.translation.z);
+                    transform.rotation = Quat::from_rotation_z(angle) * original.rotation;
+                }
+            }
+        }
+        Some(DragKind::Scale {
+            pivot,
+            start,
+            transforms,
+        }) => {
+            let (pivot, start) = (*pivot, *start);
+
+            // Don't let things get flipped or squashed to nothing
+            let factor = ((cursor_position - pivot).length()
+                / (start - pivot).length().max(f32::EPSILON))
+            .max(0.01);
+
+            let mut objects = params.p0();
+
+            for (entity, original) in transforms {
+                if let Ok(mut transform) = objects.get_mut(*entity) {
+                    let offset = original.translation.truncate() - pivot;
+
+                    transform.translation =
+                        (pivot + offset * factor).extend(original.translation.z);
+                    transform.scale = original.scale * Vec3::new(factor, factor, 1.0);
+                }
+            }
+        }
+        Some(DragKind::Box { .. }) | None => {}
+    }
+}
+
+pub fn draw_selection_gizmos(
+    mut gizmos: Gizmos,
+    selected: Query<(&Transform, Option<&ColliderAabb>), With<Selected>>,
+    projection: Single<&Projection, With<GameCamera>>,
+    drag: Res<SelectionDrag>,
+    cursor_position: Res<CursorPosition>,
+) {
+    let zoom = camera_zoom(&projection);
+
+    for (transform, aabb) in &selected {
+        let bounds = entity_bounds(transform, aabb);
+
+        gizmos.rect_2d(
+            Isometry2d::from_translation(bounds.center()),
+            bounds.size() + Vec2::splat(4.0 * zoom),
+            SELECTION_COLOR,
+        );
+    }
+
+    if let Some(bounds) = selection_bounds(
+        selected
+            .iter()
+            .map(|(transform, aabb)| entity_bounds(transform, aabb)),
+    ) {
+        let handles = SelectionHandles::new(bounds, zoom);
+
+        gizmos.line_2d(
+            Vec2::new(handles.rotate.x, bounds.max.y),
+            handles.rotate,
+            SELECTION_COLOR,
+        );
+        gizmos.circle_2d(
+            Isometry2d::from_translation(handles.rotate),
+            handles.radius,
+            SELECTION_COLOR,
+        );
+
+        gizmos.line_2d(bounds.max, handles.scale, SELECTION_COLOR);
+        gizmos.rect_2d(
+            Isometry2d::from_translation(handles.scale),
+            Vec2::splat(handles.radius * 2.0),
+            SELECTION_COLOR,
+        );
+    }
+
+    if let (Some(DragKind::Box { start }), Some(cursor_position)) = (&drag.0, **cursor_position) {
+        let rect = Rect::from_corners(*start, cursor_position);
+
+        gizmos.rect_2d(
+            Isometry2d::from_translation(rect.center()),
+            rect.size(),
+            SELECTION_COLOR.with_alpha(0.5),
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use super::selection::camera_zoom;
+use crate::{
+    camera::GameCamera,
+    cursor::CursorPosition,
+    debug::DebugSettings,
+    serialization::{GameSerializable, zones::SerializableZoneBuilder},
+};
+
+// Zone edges snap to multiples of this
+const ANGLE_STEP_DEGREES: f32 = 15.0;
+
+// In screen pixels, gets multiplied by the camera zoom
+const OBJECT_SNAP_DISTANCE: f32 = 12.0;
+
+const SNAP_COLOR: Color = Color::srgb(0.3, 0.8, 1.0);
+
+/// What placement in the editor snaps to, holding super flips `enabled` for a moment
+#[derive(Resource, Clone, Copy, PartialEq)]
+pub struct SnapSettings {
+    pub enabled: bool,
+    pub grid: bool,
+    pub angle: bool,
+    pub objects: bool,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        SnapSettings {
+            enabled: false,
+            grid: true,
+            angle: true,
+            objects: true,
+        }
+    }
+}
+
+/// Snaps points placed in the editor according to the [SnapSettings]
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct Snapping<'w, 's> {
+    settings: Res<'w, SnapSettings>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    debug_settings: Res<'w, DebugSettings>,
+    projection: Single<'w, &'static Projection, With<GameCamera>>,
+    objects: Query<
+        'w,
+        's,
+        (Entity, &'static Transform),
+        (With<GameSerializable>, Without<SerializableZoneBuilder>),
+    >,
+}
+
+impl Snapping<'_, '_> {
+    pub fn is_active(&self) -> bool {
+        // Not alt, that's for adding to the selection
+        let held = self
+            .keys
+            .any_pressed([KeyCode::SuperLeft, KeyCode::SuperRight]);
+
+        self.settings.enabled != held
+    }
+
+    /// Snaps to a nearby object's center, otherwise to the grid
+    pub fn point(&self, point: Vec2) -> Vec2 {
+        self.point_ignoring(point, &[])
+    }
+
+    /// Like [Snapping::point], but never onto the center of one of `ignored`
+    pub fn point_ignoring(&self, point: Vec2, ignored: &[Entity]) -> Vec2 {
+        if !self.is_active() {
+            return point;
+        }
+
+        self.object_center(point, ignored)
+            .or_else(|| self.settings.grid.then(|| self.grid_point(point)))
+            .unwrap_or(point)
+    }
+
+    /// Like [Snapping::point], but for the end of an edge starting at `from` so the angle snaps instead
+    pub fn edge_end(&self, from: Vec2, point: Vec2) -> Vec2 {
+        if !self.is_active() || !self.settings.angle {
+            return self.point(point);
+        }
+
+        // Closing a shape on an object beats keeping the angle
+        if let Some(center) = self.object_center(point, &[]) {
+            return center;
+        }
+
+        let offset = point - from;
+        let step = ANGLE_STEP_DEGREES.to_radians();
+        let direction = Vec2::from_angle((offset.to_angle() / step).round() * step);
+
+        from + direction * offset.dot(direction).max(0.0)
+    }
+
+    fn zoom(&self) -> f32 {
+        camera_zoom(&self.projection)
+    }
+
+    fn grid_point(&self, point: Vec2) -> Vec2 {
+        let spacing = self.debug_settings.grid_spacing(self.zoom());
+
+        (point / spacing).round() * spacing
+    }
+
+    fn object_center(&self, point: Vec2, ignored: &[Entity]) -> Option<Vec2> {
+        if !self.settings.objects {
+            return None;
+        }
+
+        let max_distance = OBJECT_SNAP_DISTANCE * self.zoom();
+
+        self.objects
+            .iter()
+            .filter(|(entity, _)| !ignored.contains(entity))
+            .map(|(_, transform)| transform.translation.truncate())
+            .filter(|center| center.distance(point) <= max_distance)
+            .min_by(|a, b| a.distance(point).total_cmp(&b.distance(point)))
+    }
+}
+
+// Shows where a click would actually go
+pub fn draw_snap_gizmos(
+    mut gizmos: Gizmos,
+    cursor_position: Res<CursorPosition>,
+    snapping: Snapping,
+) {
+    let Some(cursor_position) = **cursor_position else {
+        return;
+    };
+
+    let snapped = snapping.point(cursor_position);
+
+    if snapped != cursor_position {
+        gizmos.cross_2d(
+            Isometry2d::from_translation(snapped),
+            OBJECT_SNAP_DISTANCE * snapping.zoom(),
+            SNAP_COLOR,
+        );
+    }
+}
+
+pub fn snap_settings_ui(ui: &mut bevy_egui::egui::Ui, settings: &mut ResMut<SnapSettings>) {
+    let mut buffer = **settings;
+
+    ui.checkbox(&mut buffer.enabled, "Snapping (hold super to flip)");
+
+    // Still used while it's flipped on, so these stay editable when it's off
+    ui.checkbox(&mut buffer.grid, "Grid");
+    ui.checkbox(&mut buffer.angle, "15° zone edges");
+    ui.checkbox(&mut buffer.objects, "Object centers");
+
+    // Only write back on change so the resource isn't marked as changed every frame
+    if buffer != **settings {
+        **settings = buffer;
+    }
+}
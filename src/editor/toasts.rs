@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use crate::serialization::{
+    editor::{LoadResult, SaveResult},
+    zones::ZoneWarning,
+};
+
+// Seconds a toast stays on screen
+const TOAST_DURATION: f32 = 4.0;
+
+/// Short messages shown in the corner of the editor
+#[derive(Resource, Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+struct Toast {
+    message: String,
+    is_error: bool,
+    expires_at: f32,
+}
+
+impl Toasts {
+    pub fn push(&mut self, message: impl Into<String>, is_error: bool, now: f32) {
+        self.toasts.push(Toast {
+            message: message.into(),
+            is_error,
+            expires_at: now + TOAST_DURATION,
+        });
+    }
+}
+
+pub fn collect_toasts(
+    mut save_results: EventReader<SaveResult>,
+    mut load_results: EventReader<LoadResult>,
+    mut zone_warnings: EventReader<ZoneWarning>,
+    mut toasts: ResMut<Toasts>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+
+    for SaveResult(result) in save_results.read() {
+        match result {
+            Ok(path) => toasts.push(format!("Saved to '{}'", path.display()), false, now),
+            Err(err) => toasts.push(format!("Save failed: {err}"), true, now),
+        }
+    }
+
+    for LoadResult(result) in load_results.read() {
+        match result {
+            Ok(path) => toasts.push(format!("Loaded '{}'", path.display()), false, now),
+            Err(err) => toasts.push(format!("Load failed: {err}"), true, now),
+        }
+    }
+
+    for ZoneWarning(message) in zone_warnings.read() {
+        toasts.push(message.clone(), true, now);
+    }
+}
+
+pub fn draw_toasts(mut contexts: EguiContexts, mut toasts: ResMut<Toasts>, time: Res<Time>) {
+    let now = time.elapsed_secs();
+
+    toasts.toasts.retain(|toast| toast.expires_at > now);
+
+    if toasts.toasts.is_empty() {
+        return;
+    }
+
+    egui::Area::new(egui::Id::new("Editor Toasts"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            for toast in &toasts.toasts {
+                let color = if toast.is_error {
+                    egui::Color32::LIGHT_RED
+                } else {
+                    egui::Color32::LIGHT_GREEN
+                };
+
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.colored_label(color, toast.message.as_str());
+                });
+            }
+        });
+}
@@ -2,12 +2,23 @@ use crate::serialization;
 use bevy::prelude::*;
 use bevy_egui::{EguiContexts, egui};
 
+#[allow(clippy::too_many_arguments)]
 pub fn side_menu(
     mut contexts: EguiContexts,
     window: Single<&Window>,
     mut camera: Single<&mut Camera, With<crate::camera::GameCamera>>,
     mut save_events: EventWriter<serialization::SaveEvent>,
+    mut load_events: EventWriter<serialization::LoadEvent>,
+    mut level_browser: ResMut<super::browser::LevelBrowser>,
+    mut backup_settings: ResMut<serialization::backups::BackupSettings>,
+    mut restore_events: EventWriter<serialization::backups::RestoreBackupEvent>,
     mut serialization_data: ResMut<serialization::LevelSerializationData>,
+    mut gravity_settings: ResMut<crate::game::gravity::GravitySettings>,
+    mut prediction_limit: ResMut<crate::game::prediction::PredictionLimit>,
+    mut history: ResMut<super::history::History>,
+    mut properties: super::properties::SelectedProperties,
+    mut snap_settings: ResMut<super::snapping::SnapSettings>,
+    mut zone_collider_mode: ResMut<serialization::zones::ZoneColliderMode>,
 ) {
     // It makes the code look so much better
     use std::ops::Mul;
@@ -28,10 +39,73 @@ pub fn side_menu(
                 }
 
                 if ui.button("Load level").clicked() {
-                    warn!("Nothing here yet!");
+                    load_events.write(serialization::LoadEvent::new(path_buffer.clone()));
                 }
             });
 
+            ui.collapsing("Levels", |ui| {
+                if let Some(path) = super::browser::level_browser_ui(ui, &mut level_browser) {
+                    path_buffer = path.display().to_string();
+                    load_events.write(serialization::LoadEvent::new(path));
+                }
+            });
+
+            egui::CollapsingHeader::new("Properties")
+                .default_open(true)
+                .show(ui, |ui| {
+                    super::properties::properties_ui(ui, &mut properties, &mut history);
+                });
+
+            ui.collapsing("Snapping", |ui| {
+                super::snapping::snap_settings_ui(ui, &mut snap_settings);
+            });
+
+            ui.collapsing("Zones", |ui| {
+                zone_settings_ui(ui, &mut zone_collider_mode);
+            });
+
+            ui.collapsing("Backups", |ui| {
+                let mut count = backup_settings.count;
+
+                ui.horizontal(|ui| {
+                    ui.label("Backups to keep");
+                    ui.add(egui::DragValue::new(&mut count).range(0..=100));
+                });
+
+                if count != backup_settings.count {
+                    backup_settings.count = count;
+                }
+
+                if let Some(backup) =
+                    super::browser::backup_list_ui(ui, &mut level_browser, &serialization_data.path)
+                {
+                    restore_events.write(serialization::backups::RestoreBackupEvent::new(
+                        backup,
+                        serialization_data.path.clone(),
+                    ));
+                }
+            });
+
+            ui.collapsing("Gravity", |ui| {
+                let before = *gravity_settings;
+
+                gravity_settings_ui(ui, &mut gravity_settings);
+
+                history.record_gravity_settings(before, *gravity_settings);
+            });
+
+            ui.collapsing("Aiming", |ui| {
+                let before = *prediction_limit;
+
+                prediction_limit_ui(ui, &mut prediction_limit);
+
+                history.record_prediction_limit(before, *prediction_limit);
+            });
+
+            ui.collapsing("History", |ui| {
+                history_ui(ui, &mut history);
+            });
+
             ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::hover());
 
             serialization_data.path = path_buffer.into();
@@ -56,3 +130,116 @@ pub fn side_menu(
         ..default()
     });
 }
+
+fn gravity_settings_ui(
+    ui: &mut egui::Ui,
+    settings: &mut ResMut<crate::game::gravity::GravitySettings>,
+) {
+    use crate::game::gravity::GravityFalloff;
+
+    // Only write back on change so the resource isn't marked as changed every frame
+    let mut buffer = **settings;
+
+    ui.label("Gravitational constant");
+    ui.add(egui::DragValue::new(&mut buffer.gravitational_constant).speed(10.0));
+    ui.label("Softening");
+    ui.add(
+        egui::DragValue::new(&mut buffer.softening)
+            .speed(0.1)
+            .range(0.0..=f32::MAX),
+    );
+    ui.label("Falloff");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut buffer.falloff, GravityFalloff::InverseSquare, "1/r²");
+        ui.radio_value(&mut buffer.falloff, GravityFalloff::InverseLinear, "1/r");
+        ui.radio_value(&mut buffer.falloff, GravityFalloff::Constant, "Constant");
+    });
+
+    if buffer != **settings {
+        **settings = buffer;
+    }
+}
+
+fn prediction_limit_ui(
+    ui: &mut egui::Ui,
+    limit: &mut ResMut<crate::game::prediction::PredictionLimit>,
+) {
+    let mut buffer = **limit;
+
+    // Lower makes the level harder, zero turns the prediction off
+    ui.label("Seconds of prediction");
+    ui.add(egui::Slider::new(&mut buffer.seconds, 0.0..=10.0));
+
+    if buffer != **limit {
+        **limit = buffer;
+    }
+}
+
+fn zone_settings_ui(
+    ui: &mut egui::Ui,
+    collider_mode: &mut ResMut<serialization::zones::ZoneColliderMode>,
+) {
+    use serialization::zones::ZoneColliderMode;
+
+    let mut buffer = **collider_mode;
+
+    ui.label("Collider for new zones");
+    ui.radio_value(&mut buffer, ZoneColliderMode::Trimesh, "Trimesh");
+    ui.radio_value(&mut buffer, ZoneColliderMode::Polyline, "Outline only");
+    ui.radio_value(
+        &mut buffer,
+        ZoneColliderMode::ConvexDecomposition,
+        "Convex pieces",
+    );
+
+    if buffer != **collider_mode {
+        **collider_mode = buffer;
+    }
+}
+
+fn history_ui(ui: &mut egui::Ui, history: &mut ResMut<super::history::History>) {
+    use super::history::HistoryRequest;
+
+    ui.horizontal(|ui| {
+        if ui.button("Undo").clicked() {
+            history.request(HistoryRequest::Undo);
+        }
+
+        if ui.button("Redo").clicked() {
+            history.request(HistoryRequest::Redo);
+        }
+    });
+
+    let mut jump_to = None;
+
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            // Selecting "Start" undoes everything
+            if ui
+                .selectable_label(history.done_count() == 0, "Start")
+                .clicked()
+            {
+                jump_to = Some(0);
+            }
+
+            for (index, (label, done)) in history.entries().enumerate() {
+                let text = if done {
+                    egui::RichText::new(label)
+                } else {
+                    egui::RichText::new(label).weak()
+                };
+
+                if ui
+                    .selectable_label(index + 1 == history.done_count(), text)
+                    .clicked()
+                {
+                    jump_to = Some(index + 1);
+                }
+            }
+        });
+
+    if let Some(target) = jump_to {
+        history.request(HistoryRequest::JumpTo(target));
+    }
+}
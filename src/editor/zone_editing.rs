@@ -0,0 +1,265 @@
+use avian2d::prelude::*;
+use bevy::{prelude::*, reflect::PartialReflect};
+use bevy_egui::EguiContexts;
+
+use super::{
+    history::{EntitySnapshot, History},
+    selection::{Selected, camera_zoom},
+    snapping::Snapping,
+};
+use crate::{
+    camera::GameCamera,
+    cursor::CursorPosition,
+    serialization::{
+        colliders::SerializableCollider,
+        meshes::SerializableMesh,
+        zones::{SerializableZone, ZoneColliderMode},
+    },
+};
+
+// In screen pixels, gets multiplied by the camera zoom
+const HANDLE_SIZE: f32 = 5.0;
+
+const HANDLE_COLOR: Color = Color::srgb(0.3, 1.0, 0.5);
+
+/// The zone that's open for editing, E toggles it for the selected zone
+#[derive(Resource, Default)]
+pub struct ZoneEditing {
+    zone: Option<Entity>,
+    drag: Option<VertexDrag>,
+}
+
+struct VertexDrag {
+    label: &'static str,
+    index: usize,
+    // In world space, the zone gets rebuilt from this every frame of the drag
+    outline: Vec<Vec2>,
+    before: EntitySnapshot,
+}
+
+/// Run condition for everything else that uses the mouse in the editor
+pub fn zone_editing_inactive(editing: Res<ZoneEditing>) -> bool {
+    editing.zone.is_none()
+}
+
+type SelectedZones<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static SerializableMesh,
+        &'static SerializableCollider,
+    ),
+    With<Selected>,
+>;
+
+fn world_outline(transform: &Transform, zone: &SerializableZone) -> Vec<Vec2> {
+    zone.outline()
+        .iter()
+        .map(|point| transform.transform_point(point.extend(0.0)).truncate())
+        .collect()
+}
+
+fn vertex_at(outline: &[Vec2], point: Vec2, radius: f32) -> Option<usize> {
+    outline
+        .iter()
+        .enumerate()
+        .filter(|(_, vertex)| vertex.distance(point) <= radius)
+        .min_by(|(_, a), (_, b)| a.distance(point).total_cmp(&b.distance(point)))
+        .map(|(index, _)| index)
+}
+
+// The edge's index is the index of the vertex it starts at
+fn edge_at(outline: &[Vec2], point: Vec2, radius: f32) -> Option<(usize, Vec2)> {
+    let len = outline.len();
+
+    (0..len)
+        .map(|index| {
+            let (start, end) = (outline[index], outline[(index + 1) % len]);
+            let along = ((point - start).dot(end - start)
+                / (end - start).length_squared().max(f32::EPSILON))
+            .clamp(0.0, 1.0);
+
+            (index, start.lerp(end, along))
+        })
+        .filter(|(_, closest)| closest.distance(point) <= radius)
+        .min_by(|(_, a), (_, b)| a.distance(point).total_cmp(&b.distance(point)))
+}
+
+// Rotation and scale end up baked into the vertices, the transform is only ever a translation after
+fn rebuild_zone(
+    commands: &mut Commands,
+    entity: Entity,
+    outline: &[Vec2],
+    depth: f32,
+    mode: ZoneColliderMode,
+) {
+    let (zone, center) = SerializableZone::from_outline(outline);
+
+    commands
+        .entity(entity)
+        .insert((
+            Transform::from_translation(center.extend(depth)),
+            SerializableCollider::new(zone.collider(mode)),
+            SerializableMesh::zone(zone),
+        ))
+        .remove::<(Mesh2d, Collider)>();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn zone_editing_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    cursor_position: Res<CursorPosition>,
+    mut contexts: EguiContexts,
+    zones: SelectedZones,
+    projection: Single<&Projection, With<GameCamera>>,
+    snapping: Snapping,
+    mut editing: ResMut<ZoneEditing>,
+    mut history: ResMut<History>,
+    mut commands: Commands,
+) {
+    let ctx = contexts.ctx_mut();
+    let typing = ctx.wants_keyboard_input();
+    let over_ui = ctx.is_pointer_over_area();
+
+    // Only editable while it's the one thing selected
+    let selected_zone = zones
+        .single()
+        .ok()
+        .filter(|(_, _, mesh, _)| mesh.as_zone().is_some())
+        .map(|(entity, ..)| entity);
+
+    if !typing && keys.just_pressed(KeyCode::KeyE) {
+        editing.zone = match editing.zone {
+            Some(_) => None,
+            None => selected_zone,
+        };
+    }
+
+    if !typing && keys.just_pressed(KeyCode::Escape) {
+        editing.zone = None;
+    }
+
+    // Deselecting the zone, or undoing it away, stops editing too
+    if editing.zone != selected_zone {
+        editing.zone = None;
+    }
+
+    // Whatever was dragged still counts if editing stops halfway through
+    let finished = mouse.just_released(MouseButton::Left) || editing.zone.is_none();
+
+    if let Some(drag) = editing.drag.take_if(|_| finished) {
+        history.record_edit(drag.label, drag.before);
+    }
+
+    let (Some(zone_entity), Some(cursor_position)) = (editing.zone, **cursor_position) else {
+        return;
+    };
+
+    let Ok((entity, transform, mesh, collider)) = zones.get(zone_entity) else {
+        return;
+    };
+
+    let Some(zone) = mesh.as_zone() else {
+        return;
+    };
+
+    let mode = collider.zone_collider_mode().unwrap_or_default();
+    let depth = transform.translation.z;
+    let radius = HANDLE_SIZE * 2.0 * camera_zoom(&projection);
+
+    if let Some(drag) = editing
+        .drag
+        .as_mut()
+        .filter(|_| mouse.pressed(MouseButton::Left))
+    {
+        drag.outline[drag.index] = snapping.point(cursor_position);
+
+        rebuild_zone(&mut commands, entity, &drag.outline, depth, mode);
+    }
+
+    if over_ui {
+        return;
+    }
+
+    let before = || {
+        let components: Vec<Box<dyn PartialReflect>> = vec![
+            Box::new(*transform),
+            Box::new(mesh.clone()),
+            Box::new(collider.clone()),
+        ];
+
+        EntitySnapshot::from_components([(entity, components)])
+    };
+
+    let mut outline = world_outline(transform, zone);
+
+    if mouse.just_pressed(MouseButton::Left) {
+        if let Some(index) = vertex_at(&outline, cursor_position, radius) {
+            editing.drag = Some(VertexDrag {
+                label: "Move zone vertex",
+                index,
+                outline,
+                before: before(),
+            });
+        } else if let Some((edge, point)) = edge_at(&outline, cursor_position, radius) {
+            outline.insert(edge + 1, point);
+
+            rebuild_zone(&mut commands, entity, &outline, depth, mode);
+
+            // Keeps going as a drag so the new vertex can be put in place right away
+            editing.drag = Some(VertexDrag {
+                label: "Insert zone vertex",
+                index: edge + 1,
+                outline,
+                before: before(),
+            });
+        }
+    } else if mouse.just_pressed(MouseButton::Right) {
+        // Zones need at least a triangle
+        if let Some(index) =
+            vertex_at(&outline, cursor_position, radius).filter(|_| outline.len() > 3)
+        {
+            history.record_edit("Delete zone vertex", before());
+
+            outline.remove(index);
+
+            rebuild_zone(&mut commands, entity, &outline, depth, mode);
+        }
+    }
+}
+
+pub fn draw_zone_editing_gizmos(
+    mut gizmos: Gizmos,
+    editing: Res<ZoneEditing>,
+    zones: Query<(&Transform, &SerializableMesh)>,
+    projection: Single<&Projection, With<GameCamera>>,
+) {
+    let Some(entity) = editing.zone else {
+        return;
+    };
+
+    let outline = match &editing.drag {
+        Some(drag) => drag.outline.clone(),
+        None => match zones.get(entity) {
+            Ok((transform, mesh)) => mesh
+                .as_zone()
+                .map(|zone| world_outline(transform, zone))
+                .unwrap_or_default(),
+            Err(_) => return,
+        },
+    };
+
+    let size = HANDLE_SIZE * 2.0 * camera_zoom(&projection);
+
+    for (index, vertex) in outline.iter().enumerate() {
+        gizmos.line_2d(*vertex, outline[(index + 1) % outline.len()], HANDLE_COLOR);
+        gizmos.rect_2d(
+            Isometry2d::from_translation(*vertex),
+            Vec2::splat(size),
+            HANDLE_COLOR,
+        );
+    }
+}
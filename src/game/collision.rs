@@ -0,0 +1,547 @@
+use std::f32::consts::{PI, TAU};
+
+use avian2d::prelude::*;
+use bevy::{
+    ecs::{entity::EntityHashSet, entity_disabling::Disabled},
+    prelude::*,
+};
+
+use super::{
+    death::{DeathEvent, DeathEventsEnabled, DeathSource},
+    gravity::{Gravity, GravityLayers},
+    launch::DynamicObject,
+};
+use crate::serialization::{
+    GameSerializable, colliders::SerializableCollider, materials::SerilializableMeshMaterial,
+    meshes::SerializableMesh,
+};
+
+// Fragments fly apart at this much of the impact speed
+const FRAGMENT_SPREAD: f32 = 0.25;
+
+// Space left between fragments so they don't start out touching each other
+const FRAGMENT_GAP: f32 = 1.1;
+
+// Fragments smaller than this don't break up any further, they merge instead
+const MIN_FRAGMENT_RADIUS: f32 = 1.0;
+
+pub struct CollisionResponsePlugin;
+
+impl Plugin for CollisionResponsePlugin {
+    fn build(&self, app: &mut App) {
+        // The solver takes the hit out of the velocities, so they're saved right before it runs
+        app.add_systems(
+            FixedPostUpdate,
+            (
+                record_impact_velocities
+                    .after(PhysicsSet::Prepare)
+                    .before(PhysicsSet::StepSimulation),
+                respond_to_collisions
+                    .after(PhysicsSet::StepSimulation)
+                    .before(PhysicsSet::Sync),
+            ),
+        );
+    }
+}
+
+/// What happens to a dynamic body when it hits something, besides bouncing off
+///
+/// Level bodies that get absorbed or break apart are only disabled, and everything a collision
+/// changed about them is put back when the level resets, see [LevelBodyBackup]
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Default)]
+#[reflect(Component)]
+#[require(CollisionEventsEnabled, ImpactVelocity)]
+pub enum CollisionResponse {
+    // Plain rigid body contact
+    #[default]
+    Solid,
+    // Sticks to whatever it hits, the heavier one keeps the mass and momentum of both
+    Merge,
+    // Breaks into `pieces` smaller bodies when hit faster than `min_speed`, otherwise it's solid
+    Fragment {
+        pieces: u32,
+        min_speed: f32,
+    },
+}
+
+impl CollisionResponse {
+    pub const DEFAULT_PIECES: u32 = 4;
+    pub const DEFAULT_MIN_SPEED: f32 = 200.0;
+
+    pub fn fragment() -> Self {
+        CollisionResponse::Fragment {
+            pieces: Self::DEFAULT_PIECES,
+            min_speed: Self::DEFAULT_MIN_SPEED,
+        }
+    }
+}
+
+/// A level body from right before a collision first changed it
+///
+/// Level bodies have to be the same every attempt, unlike launched objects and fragments which are
+/// just cleared
+#[derive(Component, Clone, Copy, Debug)]
+pub struct LevelBodyBackup {
+    mass: f32,
+    position: Vec2,
+    velocity: Vec2,
+    radius: Option<f32>,
+}
+
+/// The velocity from right before the last physics step
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ImpactVelocity(Vec2);
+
+fn record_impact_velocities(mut bodies: Query<(&LinearVelocity, &mut ImpactVelocity)>) {
+    for (velocity, mut impact_velocity) in &mut bodies {
+        impact_velocity.0 = velocity.0;
+    }
+}
+
+type Bodies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static RigidBody,
+        &'static mut Mass,
+        &'static mut Position,
+        &'static mut LinearVelocity,
+        Option<&'static CollisionResponse>,
+        Option<&'static ImpactVelocity>,
+        Option<&'static mut SerializableMesh>,
+        Option<&'static mut SerializableCollider>,
+        Has<DeathEventsEnabled>,
+        Has<DynamicObject>,
+        Has<GameSerializable>,
+    ),
+>;
+
+// Everything about one side of a collision, copied out so both sides can be looked at together
+#[derive(Clone, Copy)]
+struct Impactor {
+    entity: Entity,
+    dynamic: bool,
+    mass: f32,
+    position: Vec2,
+    velocity: Vec2,
+    impact_velocity: Vec2,
+    response: CollisionResponse,
+    radius: Option<f32>,
+    mortal: bool,
+    // Spawned during play and cleared on reset, anything else has to be put back
+    transient: bool,
+}
+
+impl Impactor {
+    fn get(bodies: &Bodies, entity: Entity) -> Option<Self> {
+        let (
+            body,
+            mass,
+            position,
+            velocity,
+            response,
+            impact_velocity,
+            mesh,
+            _,
+            mortal,
+            dynamic_object,
+            level_object,
+        ) = bodies.get(entity).ok()?;
+
+        Some(Impactor {
+            entity,
+            dynamic: body.is_dynamic(),
+            mass: mass.0,
+            position: position.0,
+            velocity: velocity.0,
+            impact_velocity: impact_velocity.map_or(velocity.0, |impact| impact.0),
+            response: response.copied().unwrap_or_default(),
+            radius: mesh.and_then(|mesh| mesh.circle_radius()),
+            mortal,
+            transient: dynamic_object && !level_object,
+        })
+    }
+
+    fn backup(&self) -> LevelBodyBackup {
+        LevelBodyBackup {
+            mass: self.mass,
+            position: self.position,
+            velocity: self.velocity,
+            radius: self.radius,
+        }
+    }
+}
+
+/// Everything a fragment copies from the body it broke off of
+type FragmentLooks<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        Option<&'static SerilializableMeshMaterial>,
+        Option<&'static GravityLayers>,
+        Option<&'static CollisionLayers>,
+    ),
+>;
+
+fn respond_to_collisions(
+    mut collisions: EventReader<CollisionStarted>,
+    mut bodies: Bodies,
+    looks: FragmentLooks,
+    mut commands: Commands,
+) {
+    // Something that merged or broke apart already this step waits for the next one
+    let mut handled = EntityHashSet::default();
+
+    for CollisionStarted(first, second) in collisions.read() {
+        if handled.contains(first) || handled.contains(second) {
+            continue;
+        }
+
+        let (Some(first), Some(second)) = (
+            Impactor::get(&bodies, *first),
+            Impactor::get(&bodies, *second),
+        ) else {
+            continue;
+        };
+
+        let impact_speed = (first.impact_velocity - second.impact_velocity).length();
+
+        let mut fragmented = false;
+
+        for (body, other) in [(first, second), (second, first)] {
+            let CollisionResponse::Fragment { pieces, min_speed } = body.response else {
+                continue;
+            };
+
+            if !body.dynamic || pieces < 2 || impact_speed < min_speed {
+                continue;
+            }
+
+            let Some(radius) = body.radius else {
+                continue;
+            };
+
+            fragment(
+                &mut commands,
+                &looks,
+                body,
+                other,
+                radius,
+                pieces,
+                min_speed,
+                impact_speed,
+            );
+
+            handled.insert(body.entity);
+            fragmented = true;
+        }
+
+        if fragmented {
+            continue;
+        }
+
+        let merges = first.response == CollisionResponse::Merge
+            || second.response == CollisionResponse::Merge;
+
+        if !merges {
+            continue;
+        }
+
+        // Static and kinematic bodies can't be absorbed, otherwise the heavier one wins
+        let (survivor, absorbed) = match (first.dynamic, second.dynamic) {
+            (true, true) if second.mass > first.mass => (second, first),
+            (true, true) | (false, true) => (first, second),
+            (true, false) => (second, first),
+            (false, false) => continue,
+        };
+
+        merge(&mut commands, &mut bodies, survivor, absorbed);
+
+        handled.insert(survivor.entity);
+        handled.insert(absorbed.entity);
+    }
+}
+
+// Only the first backup counts, that's how it was when the level started
+fn back_up(commands: &mut Commands, impactor: Impactor) {
+    if !impactor.transient {
+        commands
+            .entity(impactor.entity)
+            .insert_if_new(impactor.backup());
+    }
+}
+
+fn kill(commands: &mut Commands, impactor: Impactor) {
+    // The launched object goes through the usual death so the level knows about it
+    if impactor.mortal {
+        commands.trigger_targets(DeathEvent::new(DeathSource::Collision), impactor.entity);
+    } else if impactor.transient {
+        commands.entity(impactor.entity).despawn();
+    } else {
+        back_up(commands, impactor);
+        commands.entity(impactor.entity).insert(Disabled);
+    }
+}
+
+/// The mass, position and velocity of two bodies stuck together
+///
+/// Momentum and the center of mass carry over, bodies that don't move keep not moving
+fn merged(survivor: &Impactor, absorbed: &Impactor) -> (f32, Vec2, Vec2) {
+    let total_mass = survivor.mass + absorbed.mass;
+
+    if !survivor.dynamic || total_mass <= 0.0 {
+        return (total_mass, survivor.position, survivor.velocity);
+    }
+
+    (
+        total_mass,
+        (survivor.position * survivor.mass + absorbed.position * absorbed.mass) / total_mass,
+        (survivor.velocity * survivor.mass + absorbed.velocity * absorbed.mass) / total_mass,
+    )
+}
+
+fn merge(commands: &mut Commands, bodies: &mut Bodies, survivor: Impactor, absorbed: Impactor) {
+    back_up(commands, survivor);
+
+    let Ok((_, mut mass, mut position, mut velocity, _, _, mesh, collider, ..)) =
+        bodies.get_mut(survivor.entity)
+    else {
+        return;
+    };
+
+    let (total_mass, merged_position, merged_velocity) = merged(&survivor, &absorbed);
+
+    mass.0 = total_mass;
+    position.0 = merged_position;
+    velocity.0 = merged_velocity;
+
+    // Grows by area, so it stays just as dense
+    if let Some(radius) = survivor.radius.filter(|_| survivor.mass > 0.0) {
+        let circle = Circle::new(radius * (total_mass / survivor.mass).sqrt());
+
+        if let Some(mut mesh) = mesh {
+            *mesh = SerializableMesh::primitive(circle);
+        }
+
+        if let Some(mut collider) = collider {
+            *collider = SerializableCollider::from(circle);
+        }
+
+        // The collider goes in right away so nothing falls through it for a frame
+        commands
+            .entity(survivor.entity)
+            .insert(Collider::circle(circle.radius))
+            .remove::<Mesh2d>();
+    }
+
+    kill(commands, absorbed);
+}
+
+// One of the bodies something breaks into
+#[derive(Clone, Copy, Debug)]
+struct Piece {
+    position: Vec2,
+    velocity: Vec2,
+    mass: f32,
+    radius: f32,
+    response: CollisionResponse,
+}
+
+fn fragment_pieces(
+    body: &Impactor,
+    other: &Impactor,
+    radius: f32,
+    pieces: u32,
+    min_speed: f32,
+    impact_speed: f32,
+) -> Vec<Piece> {
+    let count = pieces as f32;
+    let piece_mass = body.mass / count;
+    let piece_radius = radius / count.sqrt();
+
+    // Just far enough apart that they don't touch
+    let ring = piece_radius / (PI / count).sin() * FRAGMENT_GAP;
+
+    // Slow enough that two fragments hitting each other doesn't break them up again
+    let spread = (impact_speed * FRAGMENT_SPREAD).min(min_speed * 0.4);
+
+    // Fragments too small to break again just stick to things
+    let response = if piece_radius / count.sqrt() >= MIN_FRAGMENT_RADIUS {
+        body.response
+    } else {
+        CollisionResponse::Merge
+    };
+
+    let away = (body.position - other.position).normalize_or(Vec2::X);
+
+    (0..pieces)
+        .map(|i| {
+            // Evenly spread out, so the momentum of the pieces adds up to the original
+            let direction = Vec2::from_angle(TAU * i as f32 / count).rotate(away);
+
+            Piece {
+                position: body.position + direction * ring,
+                velocity: body.velocity + direction * spread,
+                mass: piece_mass,
+                radius: piece_radius,
+                response,
+            }
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fragment(
+    commands: &mut Commands,
+    looks: &FragmentLooks,
+    body: Impactor,
+    other: Impactor,
+    radius: f32,
+    pieces: u32,
+    min_speed: f32,
+    impact_speed: f32,
+) {
+    let Ok((transform, material, gravity_layers, collision_layers)) = looks.get(body.entity) else {
+        return;
+    };
+
+    for piece in fragment_pieces(&body, &other, radius, pieces, min_speed, impact_speed) {
+        let mut entity = commands.spawn((
+            Transform::from_translation(piece.position.extend(transform.translation.z)),
+            SerializableMesh::primitive(Circle::new(piece.radius)),
+            Collider::circle(piece.radius),
+            Mass(piece.mass),
+            LinearVelocity(piece.velocity),
+            RigidBody::Dynamic,
+            Gravity,
+            gravity_layers.copied().unwrap_or_default(),
+            piece.response,
+            // Cleared along with launched objects when the level resets
+            DynamicObject,
+        ));
+
+        if let Some(material) = material {
+            entity.insert(material.clone());
+        }
+
+        if let Some(collision_layers) = collision_layers {
+            entity.insert(*collision_layers);
+        }
+    }
+
+    kill(commands, body);
+}
+
+/// Puts level bodies back the way they were before anything merged into them or broke them apart
+#[allow(clippy::type_complexity)]
+pub fn restore_level_bodies(
+    mut bodies: Query<(
+        Entity,
+        &LevelBodyBackup,
+        &mut Mass,
+        &mut Position,
+        &mut LinearVelocity,
+        Option<&mut SerializableMesh>,
+        Option<&mut SerializableCollider>,
+        // Mentioning it is what lets disabled bodies through
+        Has<Disabled>,
+    )>,
+    mut commands: Commands,
+) {
+    for (entity, backup, mut mass, mut position, mut velocity, mesh, collider, _) in &mut bodies {
+        mass.0 = backup.mass;
+        position.0 = backup.position;
+        velocity.0 = backup.velocity;
+
+        let mut entity_commands = commands.entity(entity);
+
+        // Shrinks back down, see [merge]
+        if let Some(radius) = backup.radius {
+            let circle = Circle::new(radius);
+
+            if let Some(mut mesh) = mesh {
+                *mesh = SerializableMesh::primitive(circle);
+            }
+
+            if let Some(mut collider) = collider {
+                *collider = SerializableCollider::from(circle);
+            }
+
+            entity_commands
+                .insert(Collider::circle(radius))
+                .remove::<Mesh2d>();
+        }
+
+        entity_commands.remove::<(LevelBodyBackup, Disabled)>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn impactor(mass: f32, position: Vec2, velocity: Vec2) -> Impactor {
+        Impactor {
+            entity: Entity::PLACEHOLDER,
+            dynamic: true,
+            mass,
+            position,
+            velocity,
+            impact_velocity: velocity,
+            response: CollisionResponse::fragment(),
+            radius: Some(10.0),
+            mortal: false,
+            transient: true,
+        }
+    }
+
+    #[test]
+    fn merging_keeps_mass_and_momentum() {
+        let survivor = impactor(3.0, Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0));
+        let absorbed = impactor(1.0, Vec2::new(20.0, 0.0), Vec2::new(-20.0, 5.0));
+
+        let (mass, position, velocity) = merged(&survivor, &absorbed);
+
+        assert_eq!(mass, 4.0);
+
+        let momentum = survivor.velocity * survivor.mass + absorbed.velocity * absorbed.mass;
+        assert!((velocity * mass - momentum).length() < 1e-4);
+
+        // The center of mass doesn't jump either
+        assert!((position - Vec2::new(5.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn static_survivors_stay_put() {
+        let survivor = Impactor {
+            dynamic: false,
+            ..impactor(3.0, Vec2::ZERO, Vec2::ZERO)
+        };
+        let absorbed = impactor(1.0, Vec2::new(20.0, 0.0), Vec2::new(-20.0, 5.0));
+
+        assert_eq!(merged(&survivor, &absorbed), (4.0, Vec2::ZERO, Vec2::ZERO));
+    }
+
+    #[test]
+    fn fragments_add_up_to_the_original() {
+        let body = impactor(8.0, Vec2::new(5.0, 5.0), Vec2::new(30.0, -10.0));
+        let other = impactor(100.0, Vec2::new(-20.0, 5.0), Vec2::ZERO);
+
+        for count in [2, 3, 4, 7] {
+            let pieces = fragment_pieces(&body, &other, 10.0, count, 200.0, 400.0);
+
+            assert_eq!(pieces.len(), count as usize);
+
+            let mass: f32 = pieces.iter().map(|piece| piece.mass).sum();
+            assert!((mass - body.mass).abs() < 1e-4);
+
+            let momentum: Vec2 = pieces.iter().map(|piece| piece.velocity * piece.mass).sum();
+            assert!((momentum - body.velocity * body.mass).length() < 1e-3);
+
+            // Same total area, so just as dense
+            let area: f32 = pieces.iter().map(|piece| piece.radius.powi(2)).sum();
+            assert!((area - 100.0).abs() < 1e-3);
+        }
+    }
+}
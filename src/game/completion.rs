@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use super::{
+    GameState,
+    trigger::{GameTrigger, Triggered},
+};
+use crate::serialization::{packs::CurrentPack, progress::LevelRun};
+
+/// Sent once every [GameTrigger] in the active level has been [Triggered]
+#[derive(Event, Debug)]
+pub struct LevelComplete;
+
+/// Ask for another level to be loaded, what "next" means is up to the level loading side
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum AdvanceLevel {
+    Next,
+    Previous,
+}
+
+pub fn check_level_complete(
+    triggers: Query<Has<Triggered>, With<GameTrigger>>,
+    mut game_state: ResMut<GameState>,
+    mut level_complete: EventWriter<LevelComplete>,
+) {
+    // Sandbox launches don't count
+    if *game_state != GameState::Launched {
+        return;
+    }
+
+    if !triggers.is_empty() && triggers.iter().all(|triggered| triggered) {
+        info!("Level complete!");
+
+        *game_state = GameState::Complete;
+        level_complete.write(LevelComplete);
+    }
+}
+
+pub fn victory_screen(
+    mut contexts: EguiContexts,
+    mut advance_level: EventWriter<AdvanceLevel>,
+    pack: Option<Res<CurrentPack>>,
+    run: Res<LevelRun>,
+) {
+    egui::Window::new("Level complete!")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            if let Some(level) = pack.as_ref().and_then(|pack| pack.current()) {
+                ui.heading(level.name.as_str());
+            }
+
+            ui.label("Every trigger was hit");
+
+            if let Some((attempts, time, stars)) = run.result {
+                ui.label(format!(
+                    "{}{}",
+                    "★".repeat(stars as usize),
+                    "☆".repeat(3 - stars.min(3) as usize)
+                ));
+                ui.label(format!("{attempts} launches, {time:.2}s"));
+            }
+
+            ui.label("Press space to play again");
+
+            // Without a pack "next" just reloads the level
+            let has_next = pack.as_ref().is_none_or(|pack| pack.has_next());
+
+            if ui
+                .add_enabled(has_next, egui::Button::new("Next level"))
+                .clicked()
+            {
+                advance_level.write(AdvanceLevel::Next);
+            }
+        });
+}
+
+pub fn level_navigation_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut advance_level: EventWriter<AdvanceLevel>,
+) {
+    if keys.just_pressed(KeyCode::BracketRight) {
+        advance_level.write(AdvanceLevel::Next);
+    }
+
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        advance_level.write(AdvanceLevel::Previous);
+    }
+}
@@ -0,0 +1,330 @@
+use bevy::prelude::*;
+
+use super::GravityBody;
+
+// Past this depth bodies just get piled into the same leaf, otherwise
+// two bodies sitting on top of each other would subdivide forever
+const MAX_DEPTH: u32 = 24;
+
+/// A quadtree of gravity sources, used to approximate far away groups of
+/// bodies as a single body at their center of mass
+///
+/// Sources that push and pull can't be mixed, the "mass" here is the strength times `sign`
+pub struct QuadTree {
+    nodes: Vec<Node>,
+    sign: f32,
+}
+
+struct Node {
+    // The geometric center of the cell
+    center: Vec2,
+    half_size: f32,
+    mass: f32,
+    // Weighted by mass while building, the center of mass afterwards
+    mass_position: Vec2,
+    // Indices into the body slice, only used by leaves
+    bodies: Vec<usize>,
+    // Index of the first of the four children (they're always pushed together)
+    children: Option<usize>,
+}
+
+impl Node {
+    fn new(center: Vec2, half_size: f32) -> Self {
+        Node {
+            center,
+            half_size,
+            mass: 0.0,
+            mass_position: Vec2::ZERO,
+            bodies: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn quadrant(&self, position: Vec2) -> usize {
+        (position.x >= self.center.x) as usize + 2 * (position.y >= self.center.y) as usize
+    }
+
+    fn contains(&self, position: Vec2) -> bool {
+        (position - self.center).abs().max_element() <= self.half_size
+    }
+}
+
+impl QuadTree {
+    /// Builds a tree out of the bodies at `indices`, `sign` is -1 for a tree of repulsors
+    ///
+    /// Bodies with a strength of the other sign (or none) are left out
+    pub fn new(
+        bodies: &[GravityBody],
+        indices: impl IntoIterator<Item = usize>,
+        sign: f32,
+    ) -> Self {
+        let indices: Vec<usize> = indices
+            .into_iter()
+            .filter(|&index| bodies[index].strength * sign > 0.0)
+            .collect();
+
+        let (min, max) = indices.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), &index| {
+                (
+                    min.min(bodies[index].position),
+                    max.max(bodies[index].position),
+                )
+            },
+        );
+
+        let (center, half_size) = if indices.is_empty() {
+            (Vec2::ZERO, 1.0)
+        } else {
+            (
+                (min + max) / 2.0,
+                ((max - min).max_element() / 2.0).max(1.0),
+            )
+        };
+
+        let mut tree = QuadTree {
+            nodes: vec![Node::new(center, half_size)],
+            sign,
+        };
+
+        indices
+            .into_iter()
+            .for_each(|index| tree.insert(bodies, index));
+
+        tree.nodes.iter_mut().for_each(|node| {
+            if node.mass > 0.0 {
+                node.mass_position /= node.mass;
+            }
+        });
+
+        tree
+    }
+
+    fn weight(&self, body: &GravityBody) -> f32 {
+        body.strength * self.sign
+    }
+
+    fn insert(&mut self, bodies: &[GravityBody], index: usize) {
+        let body = bodies[index];
+        let weight = self.weight(&body);
+
+        let mut node_index = 0;
+        let mut depth = 0;
+
+        loop {
+            let node = &mut self.nodes[node_index];
+
+            node.mass += weight;
+            node.mass_position += body.position * weight;
+
+            match node.children {
+                Some(first_child) => {
+                    node_index = first_child + node.quadrant(body.position);
+                }
+                None if node.bodies.is_empty() || depth >= MAX_DEPTH => {
+                    node.bodies.push(index);
+
+                    return;
+                }
+                None => {
+                    let first_child = self.subdivide(node_index);
+
+                    // Push the old occupant(s) down a level
+                    let occupants = std::mem::take(&mut self.nodes[node_index].bodies);
+
+                    for occupant in occupants {
+                        let child = first_child
+                            + self.nodes[node_index].quadrant(bodies[occupant].position);
+                        let occupant_weight = self.weight(&bodies[occupant]);
+
+                        let child_node = &mut self.nodes[child];
+
+                        child_node.mass += occupant_weight;
+                        child_node.mass_position += bodies[occupant].position * occupant_weight;
+                        child_node.bodies.push(occupant);
+                    }
+
+                    node_index = first_child + self.nodes[node_index].quadrant(body.position);
+                }
+            }
+
+            depth += 1;
+        }
+    }
+
+    fn subdivide(&mut self, node_index: usize) -> usize {
+        let first_child = self.nodes.len();
+
+        let node = &self.nodes[node_index];
+        let quarter = node.half_size / 2.0;
+        let center = node.center;
+
+        // Same order as [Node::quadrant]
+        for offset in [
+            Vec2::new(-quarter, -quarter),
+            Vec2::new(quarter, -quarter),
+            Vec2::new(-quarter, quarter),
+            Vec2::new(quarter, quarter),
+        ] {
+            self.nodes.push(Node::new(center + offset, quarter));
+        }
+
+        self.nodes[node_index].children = Some(first_child);
+
+        first_child
+    }
+
+    /// Sums up `kernel` for every body (or group of bodies) pulling on `target`
+    ///
+    /// `kernel` takes the offset from the target to the source and the source strength.
+    /// Cells whose size over distance is smaller than `theta` get treated as one body.
+    /// The body at index `skip` is ignored so things don't pull on themselves.
+    pub fn accumulate(
+        &self,
+        bodies: &[GravityBody],
+        target: Vec2,
+        skip: usize,
+        theta: f32,
+        kernel: impl Fn(Vec2, f32) -> Vec2,
+    ) -> Vec2 {
+        let mut total = Vec2::ZERO;
+        let mut stack = vec![0];
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+
+            if node.mass <= 0.0 {
+                continue;
+            }
+
+            match node.children {
+                None => {
+                    total += node
+                        .bodies
+                        .iter()
+                        .filter(|&&index| index != skip)
+                        .map(|&index| {
+                            kernel(bodies[index].position - target, bodies[index].strength)
+                        })
+                        .sum::<Vec2>();
+                }
+                Some(first_child) => {
+                    let distance = node.mass_position.distance(target);
+
+                    if !node.contains(target) && node.half_size * 2.0 < theta * distance {
+                        total += kernel(node.mass_position - target, node.mass * self.sign);
+                    } else {
+                        stack.extend(first_child..first_child + 4);
+                    }
+                }
+            }
+        }
+
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        GravityBody, GravityLayers, GravitySettings, barnes_hut_accelerations, exact_accelerations,
+    };
+    use bevy::prelude::*;
+
+    // Deterministic bodies scattered around, no rng crate needed
+    fn scattered_bodies(count: usize) -> Vec<GravityBody> {
+        let mut seed: u32 = 12345;
+
+        let mut next = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32
+        };
+
+        (0..count)
+            .map(|_| {
+                GravityBody::new(
+                    Vec2::new(next(), next()) * 2000.0 - 1000.0,
+                    next() * 10.0 + 1.0,
+                    GravityLayers::default(),
+                )
+            })
+            .collect()
+    }
+
+    fn assert_close(approximate: &[Vec2], exact: &[Vec2], tolerance: f32) {
+        assert_eq!(approximate.len(), exact.len());
+
+        // Pulls that nearly cancel out would fail on tiny absolute errors otherwise,
+        // the approximation is only ever good relative to the pulls around it
+        let mean = exact.iter().map(|exact| exact.length()).sum::<f32>() / exact.len() as f32;
+
+        for (index, (approximate, exact)) in approximate.iter().zip(exact).enumerate() {
+            let error = approximate.distance(*exact);
+
+            assert!(
+                error <= exact.length().max(mean) * tolerance + 1e-3,
+                "body {index}: got {approximate}, expected {exact}"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_exact_within_tolerance() {
+        let bodies = scattered_bodies(200);
+        let settings = GravitySettings::default();
+
+        assert_close(
+            &barnes_hut_accelerations(&bodies, 0.5, &settings),
+            &exact_accelerations(&bodies, &settings),
+            0.05,
+        );
+    }
+
+    #[test]
+    fn zero_theta_is_exact() {
+        let bodies = scattered_bodies(100);
+        let settings = GravitySettings::default();
+
+        assert_close(
+            &barnes_hut_accelerations(&bodies, 0.0, &settings),
+            &exact_accelerations(&bodies, &settings),
+            1e-4,
+        );
+    }
+
+    #[test]
+    fn repulsors_get_their_own_tree() {
+        let mut bodies = scattered_bodies(100);
+        let settings = GravitySettings::default();
+
+        bodies.iter_mut().step_by(3).for_each(|body| {
+            body.strength = -body.strength;
+        });
+
+        assert_close(
+            &barnes_hut_accelerations(&bodies, 0.5, &settings),
+            &exact_accelerations(&bodies, &settings),
+            0.05,
+        );
+    }
+
+    #[test]
+    fn coincident_bodies_dont_subdivide_forever() {
+        let mut bodies = scattered_bodies(20);
+        let settings = GravitySettings::default();
+
+        let position = bodies[0].position;
+
+        bodies.extend((0..5).map(|_| GravityBody::new(position, 3.0, GravityLayers::default())));
+
+        let approximate = barnes_hut_accelerations(&bodies, 0.5, &settings);
+
+        assert!(
+            approximate
+                .iter()
+                .all(|acceleration| acceleration.is_finite())
+        );
+
+        assert_close(&approximate, &exact_accelerations(&bodies, &settings), 0.05);
+    }
+}
@@ -0,0 +1,519 @@
+pub mod barnes_hut;
+pub mod sources;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+pub struct GravityPlugin;
+
+impl Plugin for GravityPlugin {
+    fn build(&self, app: &mut App) {
+        // Avian steps in [FixedPostUpdate] by default, so gravity lives there too.
+        // Kicking half before and half after the step (where avian does the drift)
+        // makes the whole thing a leapfrog integrator, which keeps orbits from drifting
+        app.init_resource::<GravitySolver>()
+            .init_resource::<GravitySettings>()
+            .add_systems(
+                FixedPostUpdate,
+                (
+                    apply_gravity
+                        .after(PhysicsSet::Prepare)
+                        .before(PhysicsSet::StepSimulation),
+                    apply_gravity
+                        .after(PhysicsSet::StepSimulation)
+                        .before(PhysicsSet::Sync),
+                ),
+            );
+    }
+}
+
+// Bodies closer than this don't pull on each other (no dividing by zero here)
+const MIN_GRAVITY_DISTANCE: f32 = 0.01;
+
+// I guess I'll re-use the [PhysicsLayer] trait instead of
+// copy pasting more code
+#[derive(PhysicsLayer, Default, Copy, Clone)]
+pub enum GravityLayer {
+    #[default]
+    Main,
+    Level,
+}
+
+// So for clarity, if object a is a member of layer x with filters y and z,
+// objects with membership y and z will affect it
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Gravity;
+
+/// Which algorithm is used to sum up the gravity between bodies
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Default)]
+pub enum GravitySolver {
+    /// Every pair of bodies, O(n²)
+    #[default]
+    Exact,
+    /// Barnes-Hut quadtree approximation, O(n log n)
+    ///
+    /// `theta` is the opening angle, cells smaller than `theta * distance` are
+    /// treated as a single body. Zero is exact, around 0.5 is the usual tradeoff.
+    BarnesHut { theta: f32 },
+}
+
+impl GravitySolver {
+    pub const DEFAULT_THETA: f32 = 0.5;
+
+    pub fn barnes_hut() -> Self {
+        GravitySolver::BarnesHut {
+            theta: Self::DEFAULT_THETA,
+        }
+    }
+}
+
+/// Per level tuning of how gravity behaves, saved along with the level
+#[derive(Resource, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Resource)]
+pub struct GravitySettings {
+    pub gravitational_constant: f32,
+    /// Plummer softening length, smooths out the pull of bodies that are really close
+    pub softening: f32,
+    pub falloff: GravityFalloff,
+}
+
+impl Default for GravitySettings {
+    fn default() -> Self {
+        GravitySettings {
+            gravitational_constant: 10000.0,
+            softening: 0.0,
+            falloff: GravityFalloff::InverseSquare,
+        }
+    }
+}
+
+/// How the strength of gravity changes with distance
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Default)]
+pub enum GravityFalloff {
+    #[default]
+    InverseSquare,
+    InverseLinear,
+    Constant,
+}
+
+impl GravityFalloff {
+    pub fn exponent(self) -> i32 {
+        match self {
+            GravityFalloff::InverseSquare => 2,
+            GravityFalloff::InverseLinear => 1,
+            GravityFalloff::Constant => 0,
+        }
+    }
+}
+
+impl GravitySettings {
+    /// The acceleration a source with `source_mass` at `offset` from the target gives,
+    /// not yet multiplied by the mass of the target
+    pub fn acceleration(&self, offset: Vec2, source_mass: f32) -> Vec2 {
+        let softened_dist = (offset.length_squared() + self.softening.powi(2)).sqrt();
+
+        if softened_dist <= MIN_GRAVITY_DISTANCE {
+            return Vec2::ZERO;
+        }
+
+        // offset / dist * 1 / dist^n, with the softening folded into the distance
+        offset * source_mass * self.gravitational_constant
+            / softened_dist.powi(self.falloff.exponent() + 1)
+    }
+}
+
+/// A world-free snapshot of something taking part in the gravity simulation
+#[derive(Clone, Copy)]
+pub struct GravityBody {
+    pub position: Vec2,
+    // Multiplies how hard it gets pulled
+    pub mass: f32,
+    // How hard it pulls on everything else, the mass unless there's a [sources::GravitySource]
+    pub strength: f32,
+    pub cone: Option<sources::GravityCone>,
+    pub layers: GravityLayers,
+}
+
+impl GravityBody {
+    pub fn new(position: Vec2, mass: f32, layers: GravityLayers) -> Self {
+        GravityBody {
+            position,
+            mass,
+            strength: mass,
+            cone: None,
+            layers,
+        }
+    }
+
+    /// The acceleration this body gives something at `target`, not yet multiplied by its mass
+    pub fn pull_on(&self, target: Vec2, settings: &GravitySettings) -> Vec2 {
+        let acceleration = settings.acceleration(self.position - target, self.strength);
+
+        match self.cone {
+            Some(cone) => acceleration * cone.factor(target - self.position),
+            None => acceleration,
+        }
+    }
+}
+
+/// Calculates the acceleration of every body caused by all of the others
+pub fn gravity_accelerations(
+    bodies: &[GravityBody],
+    solver: GravitySolver,
+    settings: &GravitySettings,
+) -> Vec<Vec2> {
+    match solver {
+        GravitySolver::Exact => exact_accelerations(bodies, settings),
+        GravitySolver::BarnesHut { theta } => barnes_hut_accelerations(bodies, theta, settings),
+    }
+}
+
+fn exact_accelerations(bodies: &[GravityBody], settings: &GravitySettings) -> Vec<Vec2> {
+    let mut accelerations = vec![Vec2::ZERO; bodies.len()];
+
+    for i in 0..bodies.len() {
+        for j in i + 1..bodies.len() {
+            let (body1, body2) = (bodies[i], bodies[j]);
+
+            // If body one applies gravity to body two
+            if body1.layers.interacts_with(body2.layers) {
+                accelerations[j] += body1.pull_on(body2.position, settings) * body2.mass;
+            }
+
+            // If body two applies gravity to body one
+            if body2.layers.interacts_with(body1.layers) {
+                accelerations[i] += body2.pull_on(body1.position, settings) * body1.mass;
+            }
+        }
+    }
+
+    accelerations
+}
+
+// The tree can't filter by layers, so there's one tree for every distinct set of layers.
+// Levels only ever use a couple of them so this stays cheap. Pushing and pulling sources
+// can't share a center of mass either, so they get their own trees too.
+fn barnes_hut_accelerations(
+    bodies: &[GravityBody],
+    theta: f32,
+    settings: &GravitySettings,
+) -> Vec<Vec2> {
+    let mut groups: Vec<((GravityLayers, bool), Vec<usize>)> = vec![];
+
+    // Cones depend on where the target is, so they can't be grouped at all
+    let mut cones: Vec<usize> = vec![];
+
+    bodies.iter().enumerate().for_each(|(index, body)| {
+        if body.cone.is_some() {
+            cones.push(index);
+            return;
+        }
+
+        let key = (body.layers, body.strength < 0.0);
+
+        match groups.iter_mut().find(|(group, _)| *group == key) {
+            Some((_, indices)) => indices.push(index),
+            None => groups.push((key, vec![index])),
+        }
+    });
+
+    let trees: Vec<(GravityLayers, barnes_hut::QuadTree)> = groups
+        .into_iter()
+        .map(|((layers, repulsive), indices)| {
+            let sign = if repulsive { -1.0 } else { 1.0 };
+
+            (layers, barnes_hut::QuadTree::new(bodies, indices, sign))
+        })
+        .collect();
+
+    bodies
+        .iter()
+        .enumerate()
+        .map(|(index, body)| {
+            let from_trees = trees
+                .iter()
+                .filter(|(layers, _)| layers.interacts_with(body.layers))
+                .map(|(_, tree)| {
+                    tree.accumulate(bodies, body.position, index, theta, |offset, strength| {
+                        settings.acceleration(offset, strength)
+                    })
+                })
+                .sum::<Vec2>();
+
+            let from_cones = cones
+                .iter()
+                .filter(|&&cone| cone != index)
+                .map(|&cone| &bodies[cone])
+                .filter(|cone| cone.layers.interacts_with(body.layers))
+                .map(|cone| cone.pull_on(body.position, settings))
+                .sum::<Vec2>();
+
+            (from_trees + from_cones) * body.mass
+        })
+        .collect()
+}
+
+/// The acceleration of `body` caused by every source and field that can pull on it
+pub fn acceleration_at(
+    body: &GravityBody,
+    sources: &[GravityBody],
+    fields: &[sources::GravityField],
+    settings: &GravitySettings,
+) -> Vec2 {
+    sources
+        .iter()
+        .filter(|source| source.layers.interacts_with(body.layers))
+        .map(|source| source.pull_on(body.position, settings))
+        .sum::<Vec2>()
+        * body.mass
+        + fields
+            .iter()
+            .map(|field| field.acceleration_on(body, settings))
+            .sum::<Vec2>()
+}
+
+/// Steps `body` forward through the gravity of `sources` and returns every position it passes
+///
+/// Uses the same leapfrog integration as [apply_gravity], but the sources are assumed
+/// to stay where they are, so this is only exact for static levels.
+pub fn predict_trajectory(
+    mut body: GravityBody,
+    mut velocity: Vec2,
+    sources: &[GravityBody],
+    fields: &[sources::GravityField],
+    settings: &GravitySettings,
+    timestep: f32,
+    steps: usize,
+) -> Vec<Vec2> {
+    let mut path = Vec::with_capacity(steps + 1);
+
+    path.push(body.position);
+
+    let mut acceleration = acceleration_at(&body, sources, fields, settings);
+
+    for _ in 0..steps {
+        velocity += acceleration * timestep / 2.0;
+        body.position += velocity * timestep;
+
+        acceleration = acceleration_at(&body, sources, fields, settings);
+        velocity += acceleration * timestep / 2.0;
+
+        path.push(body.position);
+    }
+
+    path
+}
+
+/// Half of a leapfrog (kick-drift-kick) step, this runs once on each side of the physics step
+#[allow(clippy::type_complexity)]
+pub fn apply_gravity(
+    mut gravity_objects: Query<
+        (
+            Option<&Mass>,
+            &Position,
+            &mut LinearVelocity,
+            Option<&GravityLayers>,
+            Has<super::rails::OrbitRail>,
+            Option<&sources::GravitySource>,
+            Option<&Rotation>,
+            Entity,
+        ),
+        With<Gravity>,
+    >,
+    field_sources: sources::FieldSources,
+    solver: Res<GravitySolver>,
+    settings: Res<GravitySettings>,
+    time: Res<Time<Fixed>>,
+    physics_time: Res<Time<Physics>>,
+) {
+    // Avian doesn't step while paused, so no kicks either or velocities build up
+    if physics_time.is_paused() {
+        return;
+    }
+
+    // Sources without a mass can still pull, they just don't get pulled
+    let bodies: Vec<GravityBody> = gravity_objects
+        .iter()
+        .map(|(mass, position, _, layers, _, source, rotation, _)| {
+            sources::GravitySource::apply(
+                source,
+                GravityBody::new(
+                    position.0,
+                    mass.map_or(0.0, |mass| mass.0),
+                    layers.copied().unwrap_or_default(),
+                ),
+                rotation.map_or(0.0, |rotation| rotation.as_radians()),
+            )
+        })
+        .collect();
+
+    let fields = sources::gravity_fields(&field_sources);
+
+    let accelerations: Vec<Vec2> = gravity_accelerations(&bodies, *solver, &settings)
+        .into_iter()
+        .zip(&bodies)
+        .zip(gravity_objects.iter().map(|(.., entity)| entity))
+        .map(|((acceleration, body), entity)| {
+            acceleration
+                + fields
+                    .iter()
+                    // A field doesn't push the body it comes from
+                    .filter(|field| field.source != entity)
+                    .map(|field| field.acceleration_on(body, &settings))
+                    .sum::<Vec2>()
+        })
+        .collect();
+
+    // Avian scales its step by the relative speed, the kicks have to match
+    let half_step = time.timestep().as_secs_f32() * physics_time.relative_speed() / 2.0;
+
+    // Bodies on rails still pull on everything, but nothing pulls them off their rail
+    gravity_objects
+        .iter_mut()
+        .zip(accelerations)
+        .filter(|((_, _, _, _, on_rail, ..), _)| !on_rail)
+        .for_each(|((_, _, mut velocity, ..), acceleration)| {
+            velocity.0 += acceleration * half_step;
+        });
+}
+
+// Everything below here isn't fully my code
+// It's just refactored from https://github.com/Jondolf/avian/blob/main/src/collision/collider/layers.rs
+// I used a few hacky workarounds which were probably
+// unnecessary, but got the job done. I don't think there
+// is a better way to create custom layers besides
+// copy pasting and refactoring code from avian.
+
+use avian2d::prelude::LayerMask;
+
+#[derive(bevy::prelude::Component, Clone, Copy, PartialEq, Debug, Reflect)]
+#[reflect(Component)]
+pub struct GravityLayers {
+    pub memberships: LayerMask,
+
+    pub filters: LayerMask,
+}
+
+#[allow(dead_code)]
+impl GravityLayers {
+    pub const DEFAULT: Self = Self {
+        memberships: LayerMask::DEFAULT,
+
+        filters: LayerMask::ALL,
+    };
+
+    pub const ALL: Self = Self {
+        memberships: LayerMask::ALL,
+
+        filters: LayerMask::ALL,
+    };
+
+    pub const NONE: Self = Self {
+        memberships: LayerMask::NONE,
+
+        filters: LayerMask::NONE,
+    };
+
+    pub const ALL_MEMBERSHIPS: Self = Self {
+        memberships: LayerMask::ALL,
+
+        filters: LayerMask::NONE,
+    };
+
+    pub const ALL_FILTERS: Self = Self {
+        memberships: LayerMask::NONE,
+
+        filters: LayerMask::ALL,
+    };
+
+    pub fn new(memberships: impl Into<LayerMask>, filters: impl Into<LayerMask>) -> Self {
+        Self {
+            memberships: memberships.into(),
+
+            filters: filters.into(),
+        }
+    }
+
+    pub const fn from_bits(memberships: u32, filters: u32) -> Self {
+        Self {
+            memberships: LayerMask(memberships),
+
+            filters: LayerMask(filters),
+        }
+    }
+
+    pub fn interacts_with(self, other: Self) -> bool {
+        (self.memberships & other.filters) != LayerMask::NONE
+            && (other.memberships & self.filters) != LayerMask::NONE
+    }
+}
+
+impl Default for GravityLayers {
+    fn default() -> Self {
+        GravityLayers::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::launch::LaunchingObjectConfig,
+        simulation::{LevelSimulation, planet_level},
+    };
+
+    const ORBIT_RADIUS: f32 = 200.0;
+    const PLANET_MASS: f32 = 100.0;
+
+    #[test]
+    fn circular_orbits_stay_circular() {
+        let settings = GravitySettings::default();
+        let mass = LaunchingObjectConfig::default().mass;
+
+        // The pull gets multiplied by the mass of what's being pulled, so mu has it too
+        let mu = settings.gravitational_constant * PLANET_MASS * mass;
+        let speed = (mu / ORBIT_RADIUS).sqrt();
+
+        let period = std::f32::consts::TAU * ORBIT_RADIUS / speed;
+        let ticks_per_period =
+            (period / Time::<Fixed>::default().timestep().as_secs_f32()).ceil() as u32;
+
+        let energy = |position: Vec2, velocity: Vec2| {
+            velocity.length_squared() / 2.0 - mu / position.length()
+        };
+
+        let mut simulation = LevelSimulation::new(
+            &planet_level(PLANET_MASS, Vec2::X * ORBIT_RADIUS),
+            Vec2::Y * speed,
+        )
+        .unwrap();
+
+        let start_energy = energy(Vec2::X * ORBIT_RADIUS, Vec2::Y * speed);
+
+        for _ in 0..ticks_per_period * 5 {
+            simulation.step();
+
+            let world = simulation.world();
+            let launched = simulation.launched();
+
+            let position = world.get::<Position>(launched).unwrap().0;
+            let velocity = world.get::<LinearVelocity>(launched).unwrap().0;
+
+            let radius_drift = (position.length() - ORBIT_RADIUS).abs() / ORBIT_RADIUS;
+            let energy_drift = ((energy(position, velocity) - start_energy) / start_energy).abs();
+
+            assert!(
+                radius_drift < 0.01,
+                "radius drifted by {radius_drift} after {} ticks",
+                simulation.ticks()
+            );
+            assert!(
+                energy_drift < 0.01,
+                "energy drifted by {energy_drift} after {} ticks",
+                simulation.ticks()
+            );
+        }
+    }
+}
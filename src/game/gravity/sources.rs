@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+
+use super::{Gravity, GravityBody, GravityLayers, GravitySettings};
+use crate::serialization::meshes::SerializableMesh;
+
+const SOURCE_COLOR: Color = Color::srgba(0.5, 0.7, 1.0, 0.4);
+const REPULSOR_COLOR: Color = Color::srgba(1.0, 0.5, 0.4, 0.4);
+
+// How long the lines showing which way a source pulls are
+const GIZMO_LENGTH: f32 = 60.0;
+
+/// Pulls on other gravity bodies with its own strength instead of its mass
+///
+/// A negative strength pushes things away instead
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component)]
+#[require(Gravity)]
+pub struct GravitySource {
+    pub strength: f32,
+    pub shape: GravityShape,
+}
+
+impl Default for GravitySource {
+    fn default() -> Self {
+        GravitySource {
+            strength: 10.0,
+            shape: GravityShape::Point,
+        }
+    }
+}
+
+/// Which way a [GravitySource] pulls, angles are in radians and relative to the body's rotation
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Default)]
+pub enum GravityShape {
+    // Every direction, same as a plain body
+    #[default]
+    Point,
+    // Only things in front of it, fading out over `softness` past the edge of the cone
+    Cone {
+        direction: f32,
+        half_angle: f32,
+        softness: f32,
+    },
+    // The same pull everywhere inside the body's mesh. Like the rest of gravity `strength` gets
+    // multiplied by the gravitational constant and the mass of whatever is pulled, so it isn't
+    // an acceleration
+    Uniform {
+        direction: f32,
+    },
+}
+
+/// A cone shaped source, in world space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GravityCone {
+    pub direction: Vec2,
+    pub half_angle: f32,
+    pub softness: f32,
+}
+
+impl GravityCone {
+    /// How much of the pull reaches something at `offset` from the source, from 0 to 1
+    pub fn factor(&self, offset: Vec2) -> f32 {
+        let angle = self.direction.angle_to(offset).abs();
+
+        if angle <= self.half_angle {
+            1.0
+        } else if self.softness > 0.0 {
+            (1.0 - (angle - self.half_angle) / self.softness).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl GravitySource {
+    /// Puts the source's strength and cone on `body`, `rotation` is the source's rotation in radians
+    pub fn apply(source: Option<&Self>, body: GravityBody, rotation: f32) -> GravityBody {
+        let Some(source) = source else {
+            return body;
+        };
+
+        match source.shape {
+            GravityShape::Point => GravityBody {
+                strength: source.strength,
+                ..body
+            },
+            GravityShape::Cone {
+                direction,
+                half_angle,
+                softness,
+            } => GravityBody {
+                strength: source.strength,
+                cone: Some(GravityCone {
+                    direction: Vec2::from_angle(rotation + direction),
+                    half_angle,
+                    softness,
+                }),
+                ..body
+            },
+            // Fields only pull on what's inside them, see [GravityField]
+            GravityShape::Uniform { .. } => GravityBody {
+                strength: 0.0,
+                ..body
+            },
+        }
+    }
+
+    /// How hard a body pulls the same way in every direction, nothing for ones that only pull one way
+    pub fn point_strength(source: Option<&Self>, mass: f32) -> Option<f32> {
+        match source {
+            None => Some(mass),
+            Some(GravitySource {
+                strength,
+                shape: GravityShape::Point,
+            }) => Some(*strength),
+            Some(_) => None,
+        }
+    }
+}
+
+/// The area a [GravityField] covers
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldRegion {
+    Circle { center: Vec2, radius: f32 },
+    // In world space, any winding
+    Polygon(Vec<Vec2>),
+}
+
+impl FieldRegion {
+    pub fn from_mesh(mesh: &SerializableMesh, transform: &Transform) -> Option<Self> {
+        if let Some(radius) = mesh.circle_radius() {
+            return Some(FieldRegion::Circle {
+                center: transform.translation.truncate(),
+                radius: radius * transform.scale.x,
+            });
+        }
+
+        let zone = mesh.as_zone()?;
+
+        Some(FieldRegion::Polygon(
+            zone.outline()
+                .iter()
+                .map(|point| transform.transform_point(point.extend(0.0)).truncate())
+                .collect(),
+        ))
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        match self {
+            FieldRegion::Circle { center, radius } => center.distance(point) <= *radius,
+            // Counts how many edges a ray going right crosses
+            FieldRegion::Polygon(points) => {
+                points
+                    .iter()
+                    .zip(points.iter().cycle().skip(1))
+                    .filter(|(a, b)| (a.y > point.y) != (b.y > point.y))
+                    .filter(|(a, b)| point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x))
+                    .count()
+                    % 2
+                    == 1
+            }
+        }
+    }
+}
+
+/// A uniform pull inside a region, made from a [GravitySource] with [GravityShape::Uniform]
+#[derive(Clone, Debug, PartialEq)]
+pub struct GravityField {
+    // The body with the [GravitySource], it's inside its own field but doesn't get pushed by it
+    pub source: Entity,
+    pub region: FieldRegion,
+    // Before the gravitational constant and the mass, see [GravityField::acceleration_on]
+    pub acceleration: Vec2,
+    pub layers: GravityLayers,
+}
+
+impl GravityField {
+    /// Scaled by the gravitational constant and the mass of `body` like the rest of gravity,
+    /// see [GravitySettings::acceleration]
+    pub fn acceleration_on(&self, body: &GravityBody, settings: &GravitySettings) -> Vec2 {
+        if self.layers.interacts_with(body.layers) && self.region.contains(body.position) {
+            self.acceleration * settings.gravitational_constant * body.mass
+        } else {
+            Vec2::ZERO
+        }
+    }
+}
+
+pub type FieldSources<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static GravitySource,
+        &'static Transform,
+        &'static SerializableMesh,
+        Option<&'static GravityLayers>,
+    ),
+>;
+
+/// Every uniform field in the level
+pub fn gravity_fields(sources: &FieldSources) -> Vec<GravityField> {
+    sources
+        .iter()
+        .filter_map(|(entity, source, transform, mesh, layers)| {
+            let GravityShape::Uniform { direction } = source.shape else {
+                return None;
+            };
+
+            let rotation = transform.rotation.to_euler(EulerRot::XYZ).2;
+
+            Some(GravityField {
+                source: entity,
+                region: FieldRegion::from_mesh(mesh, transform)?,
+                acceleration: Vec2::from_angle(rotation + direction) * source.strength,
+                layers: layers.copied().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Shows which way the sources that aren't plain points pull
+pub fn draw_gravity_source_gizmos(
+    mut gizmos: Gizmos,
+    sources: Query<(&GravitySource, &Transform)>,
+) {
+    for (source, transform) in &sources {
+        let position = transform.translation.truncate();
+        let rotation = transform.rotation.to_euler(EulerRot::XYZ).2;
+
+        let color = if source.strength < 0.0 {
+            REPULSOR_COLOR
+        } else {
+            SOURCE_COLOR
+        };
+
+        match source.shape {
+            GravityShape::Point => {}
+            GravityShape::Cone {
+                direction,
+                half_angle,
+                ..
+            } => {
+                for edge in [-half_angle, half_angle] {
+                    gizmos.line_2d(
+                        position,
+                        position + Vec2::from_angle(rotation + direction + edge) * GIZMO_LENGTH,
+                        color,
+                    );
+                }
+            }
+            GravityShape::Uniform { direction } => {
+                // Points the way things get pulled, flipped for negative strengths
+                let pull = Vec2::from_angle(rotation + direction) * source.strength.signum();
+
+                gizmos.arrow_2d(position, position + pull * GIZMO_LENGTH, color);
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use avian2d::prelude::*;
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
+
+use super::{
+    gravity::{Gravity, GravityLayers, GravitySettings, sources::GravitySource},
+    trace::Traceable,
+};
+
+const INFLUENCE_COLOR: Color = Color::srgba(0.8, 0.6, 1.0, 0.3);
+
+/// Keeps spheres of influence and [DominantAttractor]s up to date, no drawing so it works headless
+pub struct InfluencePlugin;
+
+impl Plugin for InfluencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DominantAttractorChanged>().add_systems(
+            FixedPostUpdate,
+            (update_spheres_of_influence, track_dominant_attractors)
+                .chain()
+                .after(PhysicsSet::StepSimulation),
+        );
+    }
+}
+
+/// How far a body's gravity wins out over the body it orbits, uses the Laplace sphere of influence
+///
+/// Bodies without anything heavier pulling on them have an infinite sphere
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct SphereOfInfluence {
+    pub radius: f32,
+    // The heavier body this one is inside the sphere of, if there is one
+    pub parent: Option<Entity>,
+}
+
+/// The body a dynamic object is orbiting, the smallest sphere of influence it's inside
+#[derive(Component, Clone, Copy, Debug, PartialEq, Deref)]
+pub struct DominantAttractor(pub Entity);
+
+/// An object's [DominantAttractor] changed, going into a moon's sphere of influence moves it off the planet
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct DominantAttractorChanged {
+    pub object: Entity,
+    pub previous: Option<Entity>,
+    pub current: Option<Entity>,
+}
+
+// Launched objects are never heavy enough to be orbited, so they aren't attractors
+type Attractors<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Mass,
+        &'static Position,
+        Option<&'static GravityLayers>,
+        Option<&'static GravitySource>,
+        Option<&'static mut SphereOfInfluence>,
+    ),
+    (With<Gravity>, Without<Traceable>),
+>;
+
+pub fn update_spheres_of_influence(
+    mut attractors: Attractors,
+    settings: Res<GravitySettings>,
+    mut commands: Commands,
+) {
+    // Only things pulling the same way in every direction can be orbited
+    let bodies: Vec<(Entity, f32, Vec2, GravityLayers)> = attractors
+        .iter()
+        .filter_map(|(entity, mass, position, layers, source, _)| {
+            let strength =
+                GravitySource::point_strength(source, mass.0).filter(|strength| *strength > 0.0)?;
+
+            Some((
+                entity,
+                strength,
+                position.0,
+                layers.copied().unwrap_or_default(),
+            ))
+        })
+        .collect();
+
+    let spheres: EntityHashMap<SphereOfInfluence> = bodies
+        .iter()
+        .map(|&(entity, mass, position, layers)| {
+            // The parent is whichever heavier body pulls on this one the hardest
+            let parent = bodies
+                .iter()
+                .filter(|(other, other_mass, _, other_layers)| {
+                    *other != entity && *other_mass > mass && other_layers.interacts_with(layers)
+                })
+                .map(|&(other, other_mass, other_position, _)| {
+                    let pull = settings
+                        .acceleration(other_position - position, other_mass)
+                        .length();
+
+                    (pull, other, other_mass, other_position)
+                })
+                .max_by(|(a, ..), (b, ..)| a.total_cmp(b));
+
+            let sphere = match parent {
+                Some((_, parent, parent_mass, parent_position)) => SphereOfInfluence {
+                    radius: position.distance(parent_position) * (mass / parent_mass).powf(0.4),
+                    parent: Some(parent),
+                },
+                None => SphereOfInfluence {
+                    radius: f32::INFINITY,
+                    parent: None,
+                },
+            };
+
+            (entity, sphere)
+        })
+        .collect();
+
+    for (entity, .., sphere) in &mut attractors {
+        let Some(&new) = spheres.get(&entity) else {
+            // Turned into a repulsor or something
+            if sphere.is_some() {
+                commands.entity(entity).remove::<SphereOfInfluence>();
+            }
+
+            continue;
+        };
+
+        match sphere {
+            // Only on change so it isn't marked as changed every frame
+            Some(mut sphere) => {
+                sphere.set_if_neq(new);
+            }
+            None => {
+                commands.entity(entity).insert(new);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn track_dominant_attractors(
+    objects: Query<
+        (
+            Entity,
+            &RigidBody,
+            &Position,
+            Option<&GravityLayers>,
+            Option<&DominantAttractor>,
+        ),
+        With<Gravity>,
+    >,
+    attractors: Query<(
+        Entity,
+        &Mass,
+        &Position,
+        Option<&GravityLayers>,
+        Option<&GravitySource>,
+        &SphereOfInfluence,
+    )>,
+    settings: Res<GravitySettings>,
+    mut changed: EventWriter<DominantAttractorChanged>,
+    mut commands: Commands,
+) {
+    for (object, body, position, layers, previous) in &objects {
+        if !body.is_dynamic() {
+            continue;
+        }
+
+        let layers = layers.copied().unwrap_or_default();
+
+        // The smallest sphere it's in, or the strongest pull if it's only in infinite ones
+        let dominant = attractors
+            .iter()
+            .filter(
+                |(attractor, _, attractor_position, attractor_layers, _, sphere)| {
+                    *attractor != object
+                        && attractor_position.distance(position.0) < sphere.radius
+                        && attractor_layers
+                            .copied()
+                            .unwrap_or_default()
+                            .interacts_with(layers)
+                },
+            )
+            .map(|(attractor, mass, attractor_position, _, source, sphere)| {
+                let strength = GravitySource::point_strength(source, mass.0).unwrap_or_default();
+                let pull = settings
+                    .acceleration(attractor_position.0 - position.0, strength)
+                    .length();
+
+                (sphere.radius, pull, attractor)
+            })
+            .min_by(|(radius_a, pull_a, _), (radius_b, pull_b, _)| {
+                radius_a.total_cmp(radius_b).then(pull_b.total_cmp(pull_a))
+            })
+            .map(|(.., attractor)| attractor);
+
+        let previous = previous.map(|previous| previous.0);
+
+        if dominant == previous {
+            continue;
+        }
+
+        changed.write(DominantAttractorChanged {
+            object,
+            previous,
+            current: dominant,
+        });
+
+        match dominant {
+            Some(attractor) => {
+                commands.entity(object).insert(DominantAttractor(attractor));
+            }
+            None => {
+                commands.entity(object).remove::<DominantAttractor>();
+            }
+        }
+    }
+}
+
+pub fn draw_spheres_of_influence(
+    mut gizmos: Gizmos,
+    attractors: Query<(&Position, &SphereOfInfluence)>,
+) {
+    for (position, sphere) in &attractors {
+        if sphere.radius.is_finite() {
+            gizmos.circle_2d(
+                Isometry2d::from_translation(position.0),
+                sphere.radius,
+                INFLUENCE_COLOR,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn influence_app() -> App {
+        let mut app = App::new();
+
+        app.init_resource::<GravitySettings>()
+            .add_event::<DominantAttractorChanged>()
+            .add_systems(
+                Update,
+                (update_spheres_of_influence, track_dominant_attractors).chain(),
+            );
+
+        app
+    }
+
+    fn changes(app: &mut App) -> Vec<DominantAttractorChanged> {
+        app.world_mut()
+            .resource_mut::<Events<DominantAttractorChanged>>()
+            .drain()
+            .collect()
+    }
+
+    #[test]
+    fn moons_take_over_inside_their_sphere() {
+        let mut app = influence_app();
+
+        let planet = app
+            .world_mut()
+            .spawn((Gravity, Mass(1000.0), Position(Vec2::ZERO)))
+            .id();
+        // About 80 units of influence
+        let moon = app
+            .world_mut()
+            .spawn((Gravity, Mass(10.0), Position(Vec2::new(500.0, 0.0))))
+            .id();
+        let object = app
+            .world_mut()
+            .spawn((
+                Gravity,
+                Traceable,
+                RigidBody::Dynamic,
+                Position(Vec2::new(100.0, 0.0)),
+            ))
+            .id();
+
+        app.update();
+
+        assert_eq!(
+            changes(&mut app),
+            vec![DominantAttractorChanged {
+                object,
+                previous: None,
+                current: Some(planet),
+            }]
+        );
+
+        // Nothing new while it stays around the planet
+        app.world_mut().get_mut::<Position>(object).unwrap().0 = Vec2::new(-200.0, 0.0);
+        app.update();
+
+        assert!(changes(&mut app).is_empty());
+
+        app.world_mut().get_mut::<Position>(object).unwrap().0 = Vec2::new(480.0, 0.0);
+        app.update();
+
+        assert_eq!(
+            changes(&mut app),
+            vec![DominantAttractorChanged {
+                object,
+                previous: Some(planet),
+                current: Some(moon),
+            }]
+        );
+        assert_eq!(
+            app.world().get::<DominantAttractor>(object),
+            Some(&DominantAttractor(moon))
+        );
+
+        app.world_mut().get_mut::<Position>(object).unwrap().0 = Vec2::new(1000.0, 0.0);
+        app.update();
+
+        assert_eq!(
+            changes(&mut app),
+            vec![DominantAttractorChanged {
+                object,
+                previous: Some(moon),
+                current: Some(planet),
+            }]
+        );
+    }
+
+    #[test]
+    fn static_bodies_are_not_tracked() {
+        let mut app = influence_app();
+
+        app.world_mut()
+            .spawn((Gravity, Mass(1000.0), Position(Vec2::ZERO)));
+        let object = app
+            .world_mut()
+            .spawn((
+                Gravity,
+                Traceable,
+                RigidBody::Static,
+                Position(Vec2::new(100.0, 0.0)),
+            ))
+            .id();
+
+        app.update();
+
+        assert!(changes(&mut app).is_empty());
+        assert!(app.world().get::<DominantAttractor>(object).is_none());
+    }
+}
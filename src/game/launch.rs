@@ -177,6 +177,7 @@ pub fn launch_launching(
                     GameState::Paused
                 }
                 GameState::Sandbox => GameState::Sandbox,
+                GameState::Complete => GameState::Complete,
                 GameState::Launched => {
                     warn!("Object launched while in the launched state of the game, switching to sandbox");
                     GameState::Sandbox
@@ -1,6 +1,12 @@
+pub mod collision;
+pub mod completion;
 pub mod death;
 pub mod gravity;
+pub mod influence;
 pub mod launch;
+pub mod orbit;
+pub mod prediction;
+pub mod rails;
 pub mod trace;
 pub mod trigger;
 
@@ -12,17 +18,52 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(GameState::Launching).add_systems(
+        app.add_plugins((
+            rails::RailPlugin,
+            collision::CollisionResponsePlugin,
+            influence::InfluencePlugin,
+        ))
+        .insert_resource(GameState::Launching)
+        .init_resource::<prediction::TrajectoryPrediction>()
+        .init_resource::<prediction::PredictionLimit>()
+        .init_resource::<orbit::OrbitReadoutSettings>()
+        .add_event::<completion::LevelComplete>()
+        .add_event::<completion::AdvanceLevel>()
+        .add_systems(
             Update,
             (
                 launch::launch_launching.run_if(input_just_pressed(MouseButton::Left)),
-                launch::spawn_launching_objects.run_if(game_state_is(GameState::Launching)),
+                prediction::draw_trajectory_prediction
+                    .run_if(|prediction: Res<prediction::TrajectoryPrediction>| prediction.enabled),
+                (
+                    update_game_state_from_start_point.run_if(
+                        resource_changed::<crate::serialization::StartPoint>
+                            .and(app_state_is(AppState::Play)),
+                    ),
+                    launch::spawn_launching_objects.run_if(game_state_is(GameState::Launching)),
+                )
+                    .chain(),
                 (
                     trigger::initialize_triggered_indicators,
                     trigger::clear_triggered_indicators,
+                    completion::check_level_complete,
+                    completion::victory_screen.run_if(game_state_is(GameState::Complete)),
+                    completion::level_navigation_input,
+                    (
+                        orbit::orbit_readout_ui.run_if(
+                            |settings: Res<orbit::OrbitReadoutSettings>| settings.show_readout,
+                        ),
+                        orbit::draw_orbit_conics.run_if(
+                            |settings: Res<orbit::OrbitReadoutSettings>| settings.show_conic,
+                        ),
+                        influence::draw_spheres_of_influence.run_if(
+                            |settings: Res<orbit::OrbitReadoutSettings>| settings.show_influence,
+                        ),
+                    ),
                 )
                     .run_if(app_state_is(AppState::Play)),
                 trace::trace_object_paths,
+                gravity::sources::draw_gravity_source_gizmos.run_if(app_state_is(AppState::Editor)),
             ),
         );
     }
@@ -34,6 +75,8 @@ pub enum GameState {
     Sandbox,
     Launching,
     Launched,
+    // Every trigger was hit
+    Complete,
 }
 
 pub fn sandbox_input_handler(
@@ -86,3 +129,19 @@ pub fn clear_level(
         None => GameState::Sandbox,
     }
 }
+
+// Levels are loaded as scenes, so a new level's start point only shows up a few frames after it's loaded
+pub fn update_game_state_from_start_point(
+    starting_position: Res<crate::serialization::StartPoint>,
+    mut game_state: ResMut<GameState>,
+) {
+    // Anything further along than that is left alone
+    if !matches!(*game_state, GameState::Launching | GameState::Sandbox) {
+        return;
+    }
+
+    game_state.set_if_neq(match **starting_position {
+        Some(_) => GameState::Launching,
+        None => GameState::Sandbox,
+    });
+}
@@ -0,0 +1,437 @@
+use std::f32::consts::{PI, TAU};
+
+use avian2d::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_egui::{EguiContexts, egui};
+
+use super::{
+    gravity::{Gravity, GravityFalloff, GravitySettings, sources::GravitySource},
+    influence::DominantAttractor,
+    trace::Traceable,
+};
+
+// Escape trajectories go on forever, they're cut off at this many times the current distance
+const MAX_CONIC_DISTANCE: f32 = 10.0;
+
+const CONIC_SEGMENTS: usize = 128;
+
+const BOUND_COLOR: Color = Color::srgba(0.4, 0.9, 0.5, 0.5);
+const ESCAPE_COLOR: Color = Color::srgba(0.9, 0.5, 0.3, 0.5);
+
+/// Settings for the orbit readout shown for launched objects
+#[derive(Resource)]
+pub struct OrbitReadoutSettings {
+    pub show_readout: bool,
+    pub show_conic: bool,
+    pub show_influence: bool,
+}
+
+impl Default for OrbitReadoutSettings {
+    fn default() -> Self {
+        OrbitReadoutSettings {
+            show_readout: true,
+            show_conic: true,
+            show_influence: false,
+        }
+    }
+}
+
+/// The two body orbit something would follow if only its dominant attractor pulled on it
+///
+/// Only means anything with [GravityFalloff::InverseSquare]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitalElements {
+    // Negative for escape trajectories, infinite right on the edge of escaping
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    // Closest and furthest distances from the attractor, there's no furthest when escaping
+    pub periapsis: f32,
+    pub apoapsis: Option<f32>,
+    pub period: Option<f32>,
+    // Which way the periapsis points, in radians
+    pub argument_of_periapsis: f32,
+    pub semi_latus_rectum: f32,
+    pub clockwise: bool,
+}
+
+impl OrbitalElements {
+    /// From the position and velocity relative to the attractor, `mu` is the strength of its pull
+    pub fn from_state(position: Vec2, velocity: Vec2, mu: f32) -> Option<Self> {
+        let distance = position.length();
+
+        if mu <= 0.0 || distance <= f32::EPSILON {
+            return None;
+        }
+
+        let speed_squared = velocity.length_squared();
+        let angular_momentum = position.perp_dot(velocity);
+        let energy = speed_squared / 2.0 - mu / distance;
+
+        let eccentricity_vector =
+            ((speed_squared - mu / distance) * position - position.dot(velocity) * velocity) / mu;
+        let eccentricity = eccentricity_vector.length();
+
+        let semi_latus_rectum = angular_momentum.powi(2) / mu;
+
+        let semi_major_axis = if energy.abs() <= f32::EPSILON {
+            f32::INFINITY
+        } else {
+            -mu / (2.0 * energy)
+        };
+
+        let bound = eccentricity < 1.0;
+
+        // Circles don't have a periapsis, so anywhere on them works
+        let argument_of_periapsis = if eccentricity > 1e-4 {
+            eccentricity_vector.to_angle()
+        } else {
+            position.to_angle()
+        };
+
+        Some(OrbitalElements {
+            semi_major_axis,
+            eccentricity,
+            periapsis: semi_latus_rectum / (1.0 + eccentricity),
+            apoapsis: bound.then(|| semi_latus_rectum / (1.0 - eccentricity)),
+            period: bound.then(|| TAU * (semi_major_axis.powi(3) / mu).sqrt()),
+            argument_of_periapsis,
+            semi_latus_rectum,
+            clockwise: angular_momentum < 0.0,
+        })
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.eccentricity < 1.0
+    }
+
+    /// Distance from the attractor at `true_anomaly` radians past the periapsis
+    pub fn distance_at(&self, true_anomaly: f32) -> f32 {
+        self.semi_latus_rectum / (1.0 + self.eccentricity * true_anomaly.cos())
+    }
+
+    /// Points along the conic relative to the attractor, cut off at `max_distance`
+    pub fn conic_points(&self, max_distance: f32, segments: usize) -> Vec<Vec2> {
+        // Ellipses go all the way around, the rest only until they get too far away
+        let max_anomaly = if self.is_bound() {
+            PI
+        } else {
+            ((self.semi_latus_rectum / max_distance - 1.0) / self.eccentricity)
+                .clamp(-1.0, 1.0)
+                .acos()
+        };
+
+        (0..=segments)
+            .map(|i| -max_anomaly + 2.0 * max_anomaly * i as f32 / segments as f32)
+            .map(|anomaly| {
+                Vec2::from_angle(self.argument_of_periapsis + anomaly) * self.distance_at(anomaly)
+            })
+            .collect()
+    }
+}
+
+/// Something that's been launched, along with the orbit it's on
+pub struct LaunchedOrbit {
+    pub entity: Entity,
+    pub attractor: Entity,
+    pub attractor_position: Vec2,
+    pub distance: f32,
+    pub elements: OrbitalElements,
+}
+
+type Attractors<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Mass,
+        &'static Position,
+        Option<&'static LinearVelocity>,
+        Option<&'static GravitySource>,
+    ),
+    With<Gravity>,
+>;
+
+/// Works out the orbit of every launched object around its [DominantAttractor]
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct LaunchedOrbits<'w, 's> {
+    launched: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Mass,
+            &'static Position,
+            &'static LinearVelocity,
+            Option<&'static DominantAttractor>,
+        ),
+        (With<Traceable>, With<Gravity>),
+    >,
+    attractors: Attractors<'w, 's>,
+    settings: Res<'w, GravitySettings>,
+}
+
+impl LaunchedOrbits<'_, '_> {
+    /// Conics only come out of inverse square gravity
+    pub fn is_supported(&self) -> bool {
+        self.settings.falloff == GravityFalloff::InverseSquare
+    }
+
+    pub fn orbits(&self) -> Vec<LaunchedOrbit> {
+        if !self.is_supported() {
+            return vec![];
+        }
+
+        self.launched
+            .iter()
+            .filter_map(|(entity, mass, position, velocity, dominant)| {
+                let (attractor, attractor_mass, attractor_position, attractor_velocity, source) =
+                    self.attractors.get(**dominant?).ok()?;
+
+                let strength = GravitySource::point_strength(source, attractor_mass.0)?;
+
+                let offset = position.0 - attractor_position.0;
+                let relative_velocity =
+                    velocity.0 - attractor_velocity.map_or(Vec2::ZERO, |velocity| velocity.0);
+
+                // Accelerations get multiplied by the mass of what's being pulled, see [super::gravity::apply_gravity]
+                let mu = self.settings.gravitational_constant * strength * mass.0;
+
+                Some(LaunchedOrbit {
+                    entity,
+                    attractor,
+                    attractor_position: attractor_position.0,
+                    distance: offset.length(),
+                    elements: OrbitalElements::from_state(offset, relative_velocity, mu)?,
+                })
+            })
+            .collect()
+    }
+}
+
+pub fn draw_orbit_conics(mut gizmos: Gizmos, orbits: LaunchedOrbits) {
+    for orbit in orbits.orbits() {
+        let color = if orbit.elements.is_bound() {
+            BOUND_COLOR
+        } else {
+            ESCAPE_COLOR
+        };
+
+        let points = orbit
+            .elements
+            .conic_points(orbit.distance * MAX_CONIC_DISTANCE, CONIC_SEGMENTS)
+            .into_iter()
+            .map(|point| orbit.attractor_position + point);
+
+        gizmos.linestrip_2d(points, color);
+
+        let periapsis =
+            Vec2::from_angle(orbit.elements.argument_of_periapsis) * orbit.elements.periapsis;
+
+        gizmos.circle_2d(
+            Isometry2d::from_translation(orbit.attractor_position + periapsis),
+            2.0,
+            color,
+        );
+    }
+}
+
+pub fn orbit_readout_ui(mut contexts: EguiContexts, orbits: LaunchedOrbits) {
+    // Nothing to show before anything's been launched
+    if orbits.launched.is_empty() {
+        return;
+    }
+
+    let launched = orbits.orbits();
+
+    egui::Window::new("Orbit")
+        .resizable(false)
+        .anchor(egui::Align2::RIGHT_TOP, egui::Vec2::new(-8.0, 8.0))
+        .show(contexts.ctx_mut(), |ui| {
+            if !orbits.is_supported() {
+                ui.label("Orbits are only conics with inverse square gravity");
+                return;
+            }
+
+            for (index, orbit) in launched.iter().enumerate() {
+                if index > 0 {
+                    ui.separator();
+                }
+
+                orbit_elements_ui(ui, orbit);
+            }
+        });
+}
+
+fn orbit_elements_ui(ui: &mut egui::Ui, orbit: &LaunchedOrbit) {
+    let elements = &orbit.elements;
+
+    let or_dash = |value: Option<f32>, suffix: &str| {
+        value.map_or("-".to_string(), |value| format!("{value:.1}{suffix}"))
+    };
+
+    egui::Grid::new(("Orbit elements", orbit.entity)).show(ui, |ui| {
+        ui.label("Around");
+        ui.label(format!("{}", orbit.attractor));
+        ui.end_row();
+
+        ui.label("Status");
+        ui.label(if elements.is_bound() {
+            "Bound"
+        } else {
+            "Escaping"
+        });
+        ui.end_row();
+
+        ui.label("Semi-major axis");
+        ui.label(or_dash(
+            elements
+                .semi_major_axis
+                .is_finite()
+                .then_some(elements.semi_major_axis),
+            "",
+        ));
+        ui.end_row();
+
+        ui.label("Eccentricity");
+        ui.label(format!("{:.3}", elements.eccentricity));
+        ui.end_row();
+
+        ui.label("Periapsis");
+        ui.label(format!("{:.1}", elements.periapsis));
+        ui.end_row();
+
+        ui.label("Apoapsis");
+        ui.label(or_dash(elements.apoapsis, ""));
+        ui.end_row();
+
+        ui.label("Period");
+        ui.label(or_dash(elements.period, "s"));
+        ui.end_row();
+
+        ui.label("Direction");
+        ui.label(if elements.clockwise {
+            "Clockwise"
+        } else {
+            "Counter-clockwise"
+        });
+        ui.end_row();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::launch::LaunchingObjectConfig,
+        simulation::{LevelSimulation, planet_level},
+    };
+
+    const MU: f32 = 1000.0;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= b.abs() * 1e-3
+    }
+
+    #[test]
+    fn circles_have_no_eccentricity() {
+        let radius = 50.0;
+        let speed = (MU / radius).sqrt();
+
+        let elements = OrbitalElements::from_state(Vec2::X * radius, Vec2::Y * speed, MU).unwrap();
+
+        assert!(elements.eccentricity < 1e-4, "{elements:?}");
+        assert!(close(elements.semi_major_axis, radius), "{elements:?}");
+        assert!(!elements.clockwise);
+    }
+
+    #[test]
+    fn ellipses_have_the_right_period() {
+        let periapsis = 50.0;
+        let eccentricity = 0.5;
+
+        // Speed at the periapsis of an orbit with that eccentricity
+        let speed = (MU * (1.0 + eccentricity) / periapsis).sqrt();
+
+        let elements =
+            OrbitalElements::from_state(Vec2::X * periapsis, Vec2::NEG_Y * speed, MU).unwrap();
+
+        let semi_major_axis = periapsis / (1.0 - eccentricity);
+
+        assert!(close(elements.eccentricity, eccentricity), "{elements:?}");
+        assert!(close(elements.periapsis, periapsis), "{elements:?}");
+        assert!(close(elements.apoapsis.unwrap(), 150.0), "{elements:?}");
+        assert!(close(
+            elements.period.unwrap(),
+            TAU * (semi_major_axis.powi(3) / MU).sqrt()
+        ));
+        assert!(elements.argument_of_periapsis.abs() < 1e-3);
+        assert!(elements.clockwise);
+    }
+
+    #[test]
+    fn escape_velocity_is_not_bound() {
+        let radius = 50.0;
+        let escape_speed = (2.0 * MU / radius).sqrt();
+
+        for speed in [escape_speed, escape_speed * 1.5] {
+            let elements =
+                OrbitalElements::from_state(Vec2::X * radius, Vec2::Y * speed, MU).unwrap();
+
+            // Right at escape velocity rounding can land either side of 1
+            assert!(elements.eccentricity >= 1.0 - 1e-4, "{elements:?}");
+        }
+
+        let elements =
+            OrbitalElements::from_state(Vec2::X * radius, Vec2::Y * escape_speed * 1.5, MU)
+                .unwrap();
+
+        assert!(!elements.is_bound());
+        assert!(elements.semi_major_axis < 0.0);
+        assert_eq!(elements.apoapsis, None);
+    }
+
+    #[test]
+    fn mu_matches_the_gravity_systems() {
+        let planet_mass = 100.0;
+        let periapsis = 200.0;
+
+        let settings = GravitySettings::default();
+        let mass = LaunchingObjectConfig::default().mass;
+
+        // The same mu [LaunchedOrbits::orbits] uses, if it's wrong the simulation won't follow the conic
+        let mu = settings.gravitational_constant * planet_mass * mass;
+        let speed = (mu * 1.5 / periapsis).sqrt();
+
+        let elements =
+            OrbitalElements::from_state(Vec2::X * periapsis, Vec2::Y * speed, mu).unwrap();
+
+        let half_period = elements.period.unwrap() / 2.0;
+        let ticks = (half_period / Time::<Fixed>::default().timestep().as_secs_f32()).round();
+
+        let mut simulation = LevelSimulation::new(
+            &planet_level(planet_mass, Vec2::X * periapsis),
+            Vec2::Y * speed,
+        )
+        .unwrap();
+
+        for _ in 0..ticks as u32 {
+            simulation.step();
+        }
+
+        let position = simulation
+            .world()
+            .get::<Position>(simulation.launched())
+            .unwrap()
+            .0;
+
+        // Half an orbit later it should be at the apoapsis, on the other side
+        let apoapsis = elements.apoapsis.unwrap();
+
+        assert!(
+            (position.length() - apoapsis).abs() < apoapsis * 0.01,
+            "{position} isn't at the apoapsis {apoapsis}"
+        );
+        assert!(position.x < 0.0 && position.y.abs() < apoapsis * 0.05);
+    }
+}
@@ -0,0 +1,114 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+use super::{
+    gravity::{
+        self, Gravity, GravityBody, GravityLayers, GravitySettings,
+        sources::{self, GravitySource},
+    },
+    launch::{Launching, LaunchingObjectConfig},
+};
+
+/// Settings for the dotted path drawn while aiming
+#[derive(Resource)]
+pub struct TrajectoryPrediction {
+    pub enabled: bool,
+    // A dot is drawn every this many steps
+    pub dot_spacing: usize,
+    pub color: Color,
+}
+
+impl Default for TrajectoryPrediction {
+    fn default() -> Self {
+        TrajectoryPrediction {
+            enabled: true,
+            dot_spacing: 4,
+            color: Color::srgba(0.6, 0.6, 0.9, 0.6),
+        }
+    }
+}
+
+/// How far ahead the prediction goes, saved with the level since lower is harder
+#[derive(Resource, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Resource)]
+pub struct PredictionLimit {
+    pub seconds: f32,
+}
+
+impl Default for PredictionLimit {
+    fn default() -> Self {
+        PredictionLimit { seconds: 2.0 }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn draw_trajectory_prediction(
+    mut gizmos: Gizmos,
+    prediction: Res<TrajectoryPrediction>,
+    limit: Res<PredictionLimit>,
+    settings: Res<GravitySettings>,
+    time: Res<Time<Fixed>>,
+    cursor_position: Res<crate::cursor::CursorPosition>,
+    launching_query: Query<(&Transform, &LaunchingObjectConfig), With<Launching>>,
+    sources: Query<
+        (
+            Option<&Mass>,
+            &Position,
+            Option<&GravityLayers>,
+            Option<&GravitySource>,
+            Option<&Rotation>,
+        ),
+        With<Gravity>,
+    >,
+    field_sources: sources::FieldSources,
+) {
+    let Some(cursor_position) = **cursor_position else {
+        return;
+    };
+
+    // Bodies on rails are treated as if they stay where they are now, so the prediction drifts off near them
+    let sources: Vec<GravityBody> = sources
+        .iter()
+        .map(|(mass, position, layers, source, rotation)| {
+            GravitySource::apply(
+                source,
+                GravityBody::new(
+                    position.0,
+                    mass.map_or(0.0, |mass| mass.0),
+                    layers.copied().unwrap_or_default(),
+                ),
+                rotation.map_or(0.0, |rotation| rotation.as_radians()),
+            )
+        })
+        .collect();
+
+    let fields = sources::gravity_fields(&field_sources);
+
+    let timestep = time.timestep().as_secs_f32();
+    let steps = (limit.seconds.max(0.0) / timestep) as usize;
+
+    launching_query.iter().for_each(|(transform, config)| {
+        let body = GravityBody::new(
+            transform.translation.xy(),
+            config.mass,
+            config.gravity_layers,
+        );
+
+        // Same as in [super::launch::launch_launching]
+        let velocity = transform.translation.xy() - cursor_position;
+
+        gravity::predict_trajectory(
+            body, velocity, &sources, &fields, &settings, timestep, steps,
+        )
+        .into_iter()
+        .step_by(prediction.dot_spacing.max(1))
+        .skip(1)
+        .for_each(|position| {
+            gizmos.circle_2d(
+                Isometry2d::from_translation(position),
+                1.5,
+                prediction.color,
+            );
+        });
+    });
+}
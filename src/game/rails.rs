@@ -0,0 +1,409 @@
+use std::f32::consts::{PI, TAU};
+
+use avian2d::prelude::*;
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
+
+use super::GameState;
+use crate::{AppState, helper::app_state_is};
+
+// Rails following rails following rails... stops here in case something loops
+const MAX_RAIL_DEPTH: u32 = 8;
+
+// Past this the ellipse is basically a line and the solver stops converging
+const MAX_ECCENTRICITY: f32 = 0.99;
+
+// How many points the path gizmo is drawn with
+const RAIL_GIZMO_SEGMENTS: usize = 64;
+
+pub struct RailPlugin;
+
+impl Plugin for RailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RailPhysicsPlugin)
+            // Rails stay put while the level is being edited
+            .configure_sets(
+                FixedPostUpdate,
+                RailSystems.run_if(app_state_is(AppState::Play)),
+            )
+            .add_systems(
+                Update,
+                (
+                    reset_rail_clock.run_if(level_restarted),
+                    hold_rail_clock.run_if(resource_changed::<GameState>),
+                    (place_rails_at_start, draw_rail_gizmos).run_if(app_state_is(AppState::Editor)),
+                ),
+            );
+    }
+}
+
+/// Just the part of [RailPlugin] that moves things, for headless simulations without an [AppState]
+pub struct RailPhysicsPlugin;
+
+impl Plugin for RailPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RailClock>()
+            // Right before the physics step so avian moves them the rest of the way
+            .add_systems(
+                FixedPostUpdate,
+                follow_rails
+                    .in_set(RailSystems)
+                    .after(PhysicsSet::Prepare)
+                    .before(PhysicsSet::StepSimulation),
+            );
+    }
+}
+
+/// Where bodies get moved along their rails
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RailSystems;
+
+/// Moves a body along a fixed path instead of letting gravity move it, it still pulls on everything else
+///
+/// Bodies on rails should be [RigidBody::Kinematic] so collisions push other things out of the way
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct OrbitRail {
+    // The path goes around this body if there is one, otherwise around `anchor`
+    #[entities]
+    pub parent: Option<Entity>,
+    pub anchor: Vec2,
+    pub path: RailPath,
+    // Seconds for one trip around the path
+    pub period: f32,
+    // How far around the path it starts, from 0 to 1
+    pub phase: f32,
+}
+
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub enum RailPath {
+    // An ellipse with the parent at one focus, like a real orbit but without the chaos
+    Kepler {
+        semi_major_axis: f32,
+        eccentricity: f32,
+        // Which way the periapsis points, in radians
+        argument_of_periapsis: f32,
+        clockwise: bool,
+    },
+    // A loop that goes smoothly through every point, relative to the parent
+    Spline {
+        points: Vec<Vec2>,
+    },
+}
+
+impl OrbitRail {
+    /// A circular orbit that starts at `position`, for when a body is first put on a rail
+    pub fn circular_from(position: Vec2, radius: f32) -> Self {
+        OrbitRail {
+            parent: None,
+            anchor: position - Vec2::X * radius,
+            path: RailPath::Kepler {
+                semi_major_axis: radius,
+                eccentricity: 0.0,
+                argument_of_periapsis: 0.0,
+                clockwise: false,
+            },
+            period: 10.0,
+            phase: 0.0,
+        }
+    }
+
+    /// Where the body is relative to its parent `time` seconds into the level
+    pub fn offset_at(&self, time: f32) -> Vec2 {
+        let fraction = if self.period > 0.0 {
+            self.phase + time / self.period
+        } else {
+            self.phase
+        };
+
+        self.path.offset_at(fraction)
+    }
+}
+
+impl RailPath {
+    /// `fraction` of the way around the path, in time rather than distance
+    pub fn offset_at(&self, fraction: f32) -> Vec2 {
+        match self {
+            RailPath::Kepler {
+                semi_major_axis,
+                eccentricity,
+                argument_of_periapsis,
+                clockwise,
+            } => {
+                let eccentricity = eccentricity.clamp(0.0, MAX_ECCENTRICITY);
+                let eccentric_anomaly =
+                    solve_kepler((fraction * TAU).rem_euclid(TAU), eccentricity);
+                let semi_minor_axis = semi_major_axis * (1.0 - eccentricity.powi(2)).sqrt();
+
+                let offset = Vec2::new(
+                    semi_major_axis * (eccentric_anomaly.cos() - eccentricity),
+                    semi_minor_axis * eccentric_anomaly.sin(),
+                );
+
+                let offset = if *clockwise {
+                    Vec2::new(offset.x, -offset.y)
+                } else {
+                    offset
+                };
+
+                Vec2::from_angle(*argument_of_periapsis).rotate(offset)
+            }
+            RailPath::Spline { points } => catmull_rom_loop(points, fraction),
+        }
+    }
+}
+
+// Newton's method on M = E - e sin E, starting from pi for the really stretched out ones
+fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mut eccentric_anomaly = if eccentricity > 0.8 { PI } else { mean_anomaly };
+
+    for _ in 0..8 {
+        eccentric_anomaly -=
+            (eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - mean_anomaly)
+                / (1.0 - eccentricity * eccentric_anomaly.cos());
+    }
+
+    eccentric_anomaly
+}
+
+// Each segment takes the same time no matter how long it is
+fn catmull_rom_loop(points: &[Vec2], fraction: f32) -> Vec2 {
+    let len = points.len();
+
+    if len < 2 {
+        return points.first().copied().unwrap_or_default();
+    }
+
+    let along = fraction.rem_euclid(1.0) * len as f32;
+    let index = (along.floor() as usize).min(len - 1);
+    let t = along - index as f32;
+
+    let (p0, p1, p2, p3) = (
+        points[(index + len - 1) % len],
+        points[index],
+        points[(index + 1) % len],
+        points[(index + 2) % len],
+    );
+
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t.powi(2)
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t.powi(3))
+}
+
+/// Where the body on a rail is `time` seconds into the level, parents on rails are followed too
+///
+/// `position_of` gives the position of anything that isn't on a rail
+pub fn rail_position(
+    entity: Entity,
+    time: f32,
+    rails: &EntityHashMap<OrbitRail>,
+    position_of: &impl Fn(Entity) -> Option<Vec2>,
+) -> Option<Vec2> {
+    rail_position_inner(entity, time, rails, position_of, 0)
+}
+
+fn rail_position_inner(
+    entity: Entity,
+    time: f32,
+    rails: &EntityHashMap<OrbitRail>,
+    position_of: &impl Fn(Entity) -> Option<Vec2>,
+    depth: u32,
+) -> Option<Vec2> {
+    let Some(rail) = rails.get(&entity) else {
+        return position_of(entity);
+    };
+
+    // A parent that's gone (or loops back around) leaves the rail around its anchor
+    let origin = rail
+        .parent
+        .filter(|_| depth < MAX_RAIL_DEPTH)
+        .and_then(|parent| rail_position_inner(parent, time, rails, position_of, depth + 1))
+        .unwrap_or(rail.anchor);
+
+    Some(origin + rail.offset_at(time))
+}
+
+/// Seconds the rails have been running, starts over with the level so every attempt is the same
+#[derive(Resource, Default)]
+pub struct RailClock {
+    elapsed: f32,
+    // Stopped at the start while the player aims, so it's 0 at launch like in the solver
+    held: bool,
+}
+
+// Entering or leaving play, or relaunching after a reset
+fn level_restarted(app_state: Res<AppState>, game_state: Res<GameState>) -> bool {
+    app_state.is_changed() || (game_state.is_changed() && *game_state == GameState::Launching)
+}
+
+fn reset_rail_clock(mut clock: ResMut<RailClock>) {
+    clock.elapsed = 0.0;
+}
+
+fn hold_rail_clock(mut clock: ResMut<RailClock>, game_state: Res<GameState>) {
+    clock.held = *game_state == GameState::Launching;
+}
+
+fn follow_rails(
+    mut rails: Query<(Entity, &OrbitRail, &mut Position, &mut LinearVelocity)>,
+    others: Query<&Position, Without<OrbitRail>>,
+    mut clock: ResMut<RailClock>,
+    time: Res<Time<Fixed>>,
+) {
+    let timestep = time.timestep().as_secs_f32();
+
+    // Copied out first since rails can follow each other
+    let snapshot: EntityHashMap<OrbitRail> = rails
+        .iter()
+        .map(|(entity, rail, ..)| (entity, rail.clone()))
+        .collect();
+
+    let position_of = |entity| others.get(entity).ok().map(|position| position.0);
+
+    let step = if clock.held { 0.0 } else { timestep };
+
+    for (entity, _, mut position, mut velocity) in &mut rails {
+        let (Some(now), Some(next)) = (
+            rail_position(entity, clock.elapsed, &snapshot, &position_of),
+            rail_position(entity, clock.elapsed + step, &snapshot, &position_of),
+        ) else {
+            continue;
+        };
+
+        // The velocity is what gets it to the next position, and what things hitting it feel
+        position.0 = now;
+        velocity.0 = (next - now) / timestep;
+    }
+
+    clock.elapsed += step;
+}
+
+// So the level is saved with everything where it'll be when the level starts
+fn place_rails_at_start(
+    mut rails: Query<(Entity, &OrbitRail, &mut Transform)>,
+    others: Query<&Transform, Without<OrbitRail>>,
+) {
+    let snapshot: EntityHashMap<OrbitRail> = rails
+        .iter()
+        .map(|(entity, rail, _)| (entity, rail.clone()))
+        .collect();
+
+    let position_of = |entity| {
+        others
+            .get(entity)
+            .ok()
+            .map(|transform| transform.translation.truncate())
+    };
+
+    for (entity, _, mut transform) in &mut rails {
+        let Some(start) = rail_position(entity, 0.0, &snapshot, &position_of) else {
+            continue;
+        };
+
+        // Only on change so the transform isn't marked as changed every frame
+        if transform.translation.truncate() != start {
+            transform.translation = start.extend(transform.translation.z);
+        }
+    }
+}
+
+fn draw_rail_gizmos(
+    mut gizmos: Gizmos,
+    rails: Query<(Entity, &OrbitRail, &Transform)>,
+    others: Query<&Transform, Without<OrbitRail>>,
+) {
+    let snapshot: EntityHashMap<OrbitRail> = rails
+        .iter()
+        .map(|(entity, rail, _)| (entity, rail.clone()))
+        .collect();
+
+    let position_of = |entity| {
+        others
+            .get(entity)
+            .ok()
+            .map(|transform| transform.translation.truncate())
+    };
+
+    for (_, rail, transform) in &rails {
+        // Where the path is centered right now, the body itself is on it
+        let origin = transform.translation.truncate() - rail.offset_at(0.0);
+
+        let path: Vec<Vec2> = (0..=RAIL_GIZMO_SEGMENTS)
+            .map(|i| origin + rail.path.offset_at(i as f32 / RAIL_GIZMO_SEGMENTS as f32))
+            .collect();
+
+        gizmos.linestrip_2d(path, Color::srgba(0.6, 0.6, 1.0, 0.5));
+
+        if let Some(parent) = rail
+            .parent
+            .and_then(|parent| rail_position(parent, 0.0, &snapshot, &position_of))
+        {
+            gizmos.line_2d(
+                parent,
+                transform.translation.truncate(),
+                Color::srgba(0.6, 0.6, 1.0, 0.2),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // How far off M = E - e sin E is
+    fn kepler_error(mean_anomaly: f32, eccentricity: f32) -> f32 {
+        let eccentric_anomaly = solve_kepler(mean_anomaly, eccentricity);
+
+        (eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - mean_anomaly).abs()
+    }
+
+    #[test]
+    fn kepler_circle_is_the_mean_anomaly() {
+        for i in 0..16 {
+            let mean_anomaly = TAU * i as f32 / 16.0;
+
+            assert!((solve_kepler(mean_anomaly, 0.0) - mean_anomaly).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn kepler_converges_for_stretched_orbits() {
+        for eccentricity in [0.5, 0.9, MAX_ECCENTRICITY] {
+            for i in 0..32 {
+                let mean_anomaly = TAU * i as f32 / 32.0;
+
+                assert!(
+                    kepler_error(mean_anomaly, eccentricity) < 1e-4,
+                    "e = {eccentricity}, M = {mean_anomaly}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn catmull_rom_goes_through_every_point() {
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 10.0),
+        ];
+
+        for (i, point) in points.iter().enumerate() {
+            let fraction = i as f32 / points.len() as f32;
+
+            assert!((catmull_rom_loop(&points, fraction) - *point).length() < 1e-4);
+        }
+
+        // Loops back around to the start
+        assert!((catmull_rom_loop(&points, 1.0) - points[0]).length() < 1e-4);
+        assert!((catmull_rom_loop(&points, -0.25) - points[3]).length() < 1e-4);
+    }
+
+    #[test]
+    fn catmull_rom_with_too_few_points() {
+        assert_eq!(catmull_rom_loop(&[], 0.5), Vec2::ZERO);
+        assert_eq!(catmull_rom_loop(&[Vec2::ONE], 0.5), Vec2::ONE);
+    }
+}
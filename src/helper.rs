@@ -55,3 +55,28 @@ pub fn debug_resource<T: Resource + std::fmt::Debug>(res: Res<T>) {
 pub fn set_resource<T: Resource + Copy>(val: T) -> impl Fn(ResMut<T>) {
     move |mut res: ResMut<T>| *res = val
 }
+
+/// Writes to a temporary file next to `path` and renames it over, so a crash
+/// halfway through never leaves a half written file behind
+///
+/// The directory has to exist already
+pub fn write_atomic(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+
+    let result = std::fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&temp_path, path));
+
+    // Don't leave the temp file lying around if anything went wrong
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+
+    result
+}
@@ -5,6 +5,8 @@ pub mod editor;
 pub mod game;
 pub mod helper;
 pub mod serialization;
+pub mod simulation;
+pub mod solver;
 
 use bevy::prelude::*;
 use serialization::{GameSerializable, StartPoint};
@@ -30,8 +32,10 @@ pub fn toggle_app_state(mut state: ResMut<AppState>) {
     }
 }
 
-pub fn clear_level(mut commands: Commands, query: Query<Entity, With<GameSerializable>>) {
-    query
-        .iter()
-        .for_each(|x| commands.get_entity(x).unwrap().despawn());
+// Goes through the history, so it can be undone and nothing in there points at dead entities
+pub fn clear_level(
+    query: Query<Entity, With<GameSerializable>>,
+    mut history: ResMut<editor::history::History>,
+) {
+    history.despawn("Clear level", query.iter());
 }
@@ -9,7 +9,10 @@ use bevy_orbit::{
     cursor::CursorPlugin,
     debug::{DebugPlugin, toggle_debug_ui},
     editor::EditorPlugin,
-    game::{GamePlugin, clear_level, death::DeathEvent, gravity::GravityPlugin},
+    game::{
+        GamePlugin, clear_level, collision::restore_level_bodies, completion::AdvanceLevel,
+        death::DeathEvent, gravity::GravityPlugin,
+    },
     helper::app_state_is,
     serialization::SerializationPlugin,
     *,
@@ -60,6 +63,23 @@ fn main() {
                     .chain()
                     .run_if(resource_changed::<AppState>)
                     .run_if(app_state_is(Play)),
+                (
+                    serialization::editor::apply_load_event,
+                    serialization::editor::remove_level_entities,
+                    serialization::editor::spawn_temp_scene,
+                )
+                    .chain()
+                    .run_if(on_event::<serialization::LoadEvent>)
+                    .run_if(app_state_is(Editor)),
+                (
+                    serialization::packs::navigate_pack,
+                    clear_level,
+                    serialization::game::remove_active_level,
+                    serialization::game::load_active_level,
+                )
+                    .chain()
+                    .run_if(on_event::<AdvanceLevel>)
+                    .run_if(app_state_is(Play)),
             ),
         )
         // Post Update Systems
@@ -70,7 +90,8 @@ fn main() {
                     .run_if(input_pressed(KeyCode::ShiftLeft))
                     .run_if(input_just_pressed(KeyCode::Space))
                     .run_if(app_state_is(Editor)),
-                clear_level
+                (clear_level, restore_level_bodies)
+                    .chain()
                     .run_if(input_just_pressed(KeyCode::Space))
                     .run_if(not(input_pressed(KeyCode::ShiftLeft))),
             ),
@@ -0,0 +1,192 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+
+use super::{LoadEvent, editor::SaveError, editor::SaveResult};
+
+pub struct BackupPlugin;
+
+impl Plugin for BackupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BackupSettings>()
+            .init_resource::<PendingRestores>()
+            .add_event::<RestoreBackupEvent>()
+            .add_systems(Update, (restore_backups, finish_restores).chain());
+    }
+}
+
+/// How many old copies of each level are kept around when saving over it
+#[derive(Resource, Debug)]
+pub struct BackupSettings {
+    pub count: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings { count: 5 }
+    }
+}
+
+/// An old copy of a level, saved next to it as `<level>.<unix millis>.bak`
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: SystemTime,
+}
+
+fn backup_path(level_path: &Path, created: SystemTime) -> PathBuf {
+    let millis = created
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    let mut path = level_path.as_os_str().to_owned();
+    path.push(format!(".{millis}.bak"));
+
+    PathBuf::from(path)
+}
+
+/// Every backup of a level, newest first
+pub fn list_backups(level_path: &Path) -> Vec<Backup> {
+    let (Some(directory), Some(level_name)) = (
+        level_path.parent(),
+        level_path.file_name().and_then(|name| name.to_str()),
+    ) else {
+        return vec![];
+    };
+
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return vec![];
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+
+            // The timestamp is in the name so copying the folder around doesn't lose it
+            let millis = file_name
+                .to_str()?
+                .strip_prefix(level_name)?
+                .strip_prefix('.')?
+                .strip_suffix(".bak")?
+                .parse()
+                .ok()?;
+
+            Some(Backup {
+                path: entry.path(),
+                created: SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+            })
+        })
+        .collect();
+
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created));
+
+    backups
+}
+
+/// Copies the level to a new backup, then deletes all but the newest `count`
+///
+/// Does nothing if there's no level there yet
+pub fn backup_level(level_path: &Path, count: usize) -> std::io::Result<()> {
+    if count == 0 || !level_path.is_file() {
+        return Ok(());
+    }
+
+    std::fs::copy(level_path, backup_path(level_path, SystemTime::now()))?;
+
+    for old_backup in list_backups(level_path).into_iter().skip(count) {
+        std::fs::remove_file(old_backup.path)?;
+    }
+
+    Ok(())
+}
+
+/// Puts a backup back in place of its level and reloads it in the editor
+#[derive(Event)]
+pub struct RestoreBackupEvent {
+    backup: PathBuf,
+    // Relative to `assets/`
+    level: PathBuf,
+}
+
+impl RestoreBackupEvent {
+    pub fn new<U: Into<PathBuf>, V: Into<PathBuf>>(backup: U, level: V) -> Self {
+        RestoreBackupEvent {
+            backup: backup.into(),
+            level: level.into(),
+        }
+    }
+}
+
+// The asset server caches levels, so a restored level has to be reloaded before it's spawned
+#[derive(Resource, Default)]
+struct PendingRestores(Vec<Handle<DynamicScene>>);
+
+fn restore_backups(
+    mut events: EventReader<RestoreBackupEvent>,
+    mut pending: ResMut<PendingRestores>,
+    mut save_results: EventWriter<SaveResult>,
+    settings: Res<BackupSettings>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in events.read() {
+        let level_path = PathBuf::from("assets/").join(&event.level);
+
+        // Restoring counts as a save, so whatever was there gets backed up too
+        let result = std::fs::read(&event.backup).and_then(|contents| {
+            backup_level(&level_path, settings.count)?;
+            crate::helper::write_atomic(&level_path, &contents)
+        });
+
+        if let Err(err) = result {
+            let err = SaveError::from_io(&level_path, err);
+
+            error!("Failed to restore backup: {err}");
+            save_results.write(SaveResult(Err(err)));
+            continue;
+        }
+
+        info!(
+            "Restored '{}' from '{}'",
+            level_path.display(),
+            event.backup.display()
+        );
+
+        let handle: Handle<DynamicScene> = asset_server.load(event.level.clone());
+
+        if asset_server.is_loaded(&handle) {
+            asset_server.reload(event.level.clone());
+        }
+
+        pending.0.push(handle);
+    }
+}
+
+fn finish_restores(
+    mut asset_events: EventReader<AssetEvent<DynamicScene>>,
+    mut pending: ResMut<PendingRestores>,
+    mut load_events: EventWriter<LoadEvent>,
+) {
+    for event in asset_events.read() {
+        let (AssetEvent::Modified { id } | AssetEvent::LoadedWithDependencies { id }) = event
+        else {
+            continue;
+        };
+
+        pending.0.retain(|handle| {
+            if handle.id() != *id {
+                return true;
+            }
+
+            if let Some(path) = handle.path() {
+                load_events.write(LoadEvent::new(path.path()));
+            }
+
+            false
+        });
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 
 // This is just a resilient version of ColliderConstructor
-#[derive(Component, Reflect)]
+#[derive(Component, Reflect, Clone)]
 #[reflect(Component)]
 pub struct SerializableCollider(avian2d::prelude::ColliderConstructor);
 
@@ -9,6 +9,28 @@ impl SerializableCollider {
     pub fn new(collider: avian2d::prelude::ColliderConstructor) -> Self {
         SerializableCollider(collider)
     }
+
+    pub fn circle_radius(&self) -> Option<f32> {
+        match self.0 {
+            avian2d::prelude::ColliderConstructor::Circle { radius } => Some(radius),
+            _ => None,
+        }
+    }
+
+    /// Which kind of zone collider this is, if it's one a zone could have
+    pub fn zone_collider_mode(&self) -> Option<super::zones::ZoneColliderMode> {
+        use super::zones::ZoneColliderMode;
+        use avian2d::prelude::ColliderConstructor;
+
+        match self.0 {
+            ColliderConstructor::Trimesh { .. } => Some(ZoneColliderMode::Trimesh),
+            ColliderConstructor::Polyline { .. } => Some(ZoneColliderMode::Polyline),
+            ColliderConstructor::ConvexDecomposition { .. } => {
+                Some(ZoneColliderMode::ConvexDecomposition)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl From<Circle> for SerializableCollider {
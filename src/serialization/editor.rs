@@ -1,88 +1,280 @@
-use std::{fs::File, path::PathBuf};
+use std::path::PathBuf;
 
-use bevy::{prelude::*, reflect::TypeRegistry};
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
 
-use super::{ExternalSerializableTypes, GameSerializable, InternalSerializableTypes};
+use super::{GameSerializable, serializable_components_type_registry};
+
+/// A scene builder that only lets level components through, resources are up to the caller
+pub fn level_scene_builder(world: &World) -> DynamicSceneBuilder<'_> {
+    // Dynamic programming when
+    DynamicSceneBuilder::from_world(world)
+        .deny_all_resources()
+        // Internal types
+        .allow_component::<crate::game::gravity::Gravity>()
+        .allow_component::<crate::game::gravity::sources::GravitySource>()
+        .allow_component::<crate::game::gravity::GravityLayers>()
+        .allow_component::<crate::game::trigger::GameTrigger>()
+        .allow_component::<crate::game::death::KillOnCollision>()
+        .allow_component::<crate::serialization::LevelObject>()
+        .allow_component::<crate::game::launch::DynamicObject>()
+        .allow_component::<crate::game::rails::OrbitRail>()
+        .allow_component::<crate::game::collision::CollisionResponse>()
+        .allow_component::<crate::serialization::colliders::SerializableCollider>()
+        .allow_component::<crate::serialization::meshes::SerializableMesh>()
+        .allow_component::<crate::serialization::materials::SerilializableMeshMaterial>()
+        .allow_component::<crate::serialization::GameSerializable>()
+        // External types
+        .allow_component::<Transform>()
+        .allow_component::<avian2d::prelude::CollisionLayers>()
+        .allow_component::<avian2d::prelude::Mass>()
+        .allow_component::<avian2d::prelude::RigidBody>()
+        .allow_component::<avian2d::prelude::LinearVelocity>()
+}
 
 // Only for use in editor mode
 pub fn serialize_objects(
     mut events: EventReader<super::SaveEvent>,
     world: &World,
     entities: Query<Entity, With<super::GameSerializable>>,
+    mut commands: Commands,
 ) {
     for event in events.read() {
-        // Dynamic programming when
-        let scene_builder = DynamicSceneBuilder::from_world(world)
-            // Internal types
-            .allow_component::<crate::game::gravity::Gravity>()
-            .allow_component::<crate::game::gravity::GravityLayers>()
-            .allow_component::<crate::game::trigger::GameTrigger>()
-            .allow_component::<crate::game::death::KillOnCollision>()
-            .allow_component::<crate::serialization::LevelObject>()
-            .allow_component::<crate::game::launch::DynamicObject>()
-            .allow_component::<crate::serialization::colliders::SerializableCollider>()
-            .allow_component::<crate::serialization::meshes::SerializableMesh>()
-            .allow_component::<crate::serialization::materials::SerilializableMeshMaterial>()
-            .allow_component::<crate::serialization::GameSerializable>()
-            // External types
-            .allow_component::<Transform>()
-            .allow_component::<avian2d::prelude::CollisionLayers>()
-            .allow_component::<avian2d::prelude::Mass>()
-            .allow_component::<avian2d::prelude::RigidBody>()
+        let scene_builder = level_scene_builder(world)
             // Resources
-            .allow_resource::<crate::serialization::StartPoint>();
+            .allow_resource::<crate::serialization::StartPoint>()
+            .allow_resource::<crate::game::gravity::GravitySettings>()
+            .allow_resource::<crate::game::prediction::PredictionLimit>()
+            .allow_resource::<crate::serialization::migration::LevelFormat>();
 
         let scene = scene_builder
             .extract_entities(entities.iter())
             .extract_resources()
             .build();
 
-        // info!(
-        //     "Scene: {:?}",
-        //     scene
-        //         .entities
-        //         .iter()
-        //         .for_each(|entity| info!("{:?}", entity.components))
-        // );
+        let mut target_path = PathBuf::from("assets/");
 
-        let type_registry = serializable_components_type_registry();
+        target_path.push(event.path.clone());
 
-        let serialized = scene.serialize(&type_registry).unwrap_or_else(|err| {
-            error!("Failed to serialize scene: {err}");
-            String::new()
-        });
+        // Failing here means nothing gets written, so a bad save never clobbers the old file
+        let serialized = match serialize_scene(&scene) {
+            Ok(serialized) => serialized,
+            Err(err) => {
+                error!("Failed to serialize scene: {err}");
+                commands.send_event(SaveResult(Err(err)));
+                continue;
+            }
+        };
 
-        use std::io::Write;
+        let backup_count = world.resource::<super::backups::BackupSettings>().count;
 
-        let mut target_path = PathBuf::from("assets/");
+        let task = bevy::tasks::IoTaskPool::get()
+            .spawn(async move { write_level(target_path, serialized, backup_count) });
 
-        target_path.push(event.path.clone());
+        commands.spawn(SaveTask(task));
+    }
+}
+
+/// Turns a scene into ron with only the serializable types, used for levels and the clipboard
+pub fn serialize_scene(scene: &DynamicScene) -> Result<String, SaveError> {
+    let type_registry = serializable_components_type_registry();
 
-        bevy::tasks::IoTaskPool::get()
-            .spawn(async move {
-                File::create(target_path.clone())
-                    .unwrap_or_else(|err| {
-                        panic!("Failed to open file '{}': {err}", target_path.display())
-                    })
-                    .write_all(serialized.as_bytes())
-                    .unwrap_or_else(|err| {
-                        panic!(
-                            "Failed to write data to file '{}': {err}",
-                            target_path.display()
-                        )
-                    });
+    // The serializer's own message for this is vague, so check up front
+    let unregistered = scene
+        .entities
+        .iter()
+        .flat_map(|entity| entity.components.iter())
+        .chain(scene.resources.iter())
+        .filter_map(|value| value.get_represented_type_info())
+        .find(|info| !type_registry.contains(info.type_id()));
+
+    if let Some(info) = unregistered {
+        return Err(SaveError::UnknownType(info.type_path().to_string()));
+    }
+
+    scene
+        .serialize(&type_registry)
+        .map_err(|err| SaveError::Reflect(err.to_string()))
+}
+
+fn write_level(
+    target_path: PathBuf,
+    serialized: String,
+    backup_count: usize,
+) -> Result<PathBuf, SaveError> {
+    // Losing a backup isn't worth losing the save over
+    if let Err(err) = super::backups::backup_level(&target_path, backup_count) {
+        warn!(
+            "Failed to back up '{}' before saving: {err}",
+            target_path.display()
+        );
+    }
+
+    crate::helper::write_atomic(&target_path, serialized.as_bytes())
+        .map_err(|err| SaveError::from_io(&target_path, err))?;
+
+    Ok(target_path)
+}
+
+/// Why a level couldn't be saved
+#[derive(Debug)]
+pub enum SaveError {
+    // The folder the level would go in doesn't exist
+    MissingDirectory(PathBuf),
+    PermissionDenied(PathBuf),
+    Io(PathBuf, std::io::Error),
+    // A type in the level isn't in [serializable_components_type_registry]
+    UnknownType(String),
+    // Reflection couldn't turn the level into ron
+    Reflect(String),
+}
 
+impl SaveError {
+    pub(super) fn from_io(path: &std::path::Path, err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => {
+                SaveError::MissingDirectory(path.parent().map(PathBuf::from).unwrap_or_default())
+            }
+            std::io::ErrorKind::PermissionDenied => SaveError::PermissionDenied(path.into()),
+            _ => SaveError::Io(path.into(), err),
+        }
+    }
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::MissingDirectory(path) => {
+                write!(f, "The directory '{}' doesn't exist", path.display())
+            }
+            SaveError::PermissionDenied(path) => {
+                write!(f, "Not allowed to write to '{}'", path.display())
+            }
+            SaveError::Io(path, err) => write!(f, "Failed to write '{}': {err}", path.display()),
+            SaveError::UnknownType(type_path) => {
+                write!(f, "'{type_path}' isn't registered as serializable")
+            }
+            SaveError::Reflect(err) => write!(f, "Failed to serialize the level: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// Why a level couldn't be loaded
+#[derive(Debug)]
+pub enum LoadError {
+    NotFound(PathBuf),
+    Io(PathBuf, std::io::Error),
+    // The file is there but isn't a level this version understands
+    Parse(PathBuf, bevy::scene::ron::error::SpannedError),
+    // A type in the level isn't registered as serializable
+    UnknownType(PathBuf, String),
+    // The level was read fine but couldn't be put into the world
+    Spawn(PathBuf, bevy::scene::SceneSpawnError),
+    // Anything else the asset server ran into
+    Asset(PathBuf, bevy::asset::AssetLoadError),
+}
+
+impl LoadError {
+    fn from_read(path: PathBuf, err: super::game::LevelReadError) -> Self {
+        use super::game::LevelReadError;
+
+        match err {
+            LevelReadError::Io(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                LoadError::NotFound(path)
+            }
+            LevelReadError::Io(err) => LoadError::Io(path, err),
+            LevelReadError::Ron(err) => LoadError::Parse(path, err),
+            LevelReadError::UnknownType(type_path) => LoadError::UnknownType(path, type_path),
+        }
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::NotFound(path) => write!(f, "No level at '{}'", path.display()),
+            LoadError::Io(path, err) => write!(f, "Failed to read '{}': {err}", path.display()),
+            LoadError::Parse(path, err) => {
+                write!(f, "Failed to parse '{}': {err}", path.display())
+            }
+            LoadError::UnknownType(path, type_path) => write!(
+                f,
+                "'{}' has '{type_path}' in it, which isn't registered as serializable",
+                path.display()
+            ),
+            LoadError::Spawn(path, err) => {
+                write!(f, "Failed to spawn '{}': {err}", path.display())
+            }
+            LoadError::Asset(path, err) => {
+                write!(f, "Failed to load '{}': {err}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::NotFound(_) | LoadError::UnknownType(..) => None,
+            LoadError::Io(_, err) => Some(err),
+            LoadError::Parse(_, err) => Some(err),
+            LoadError::Spawn(_, err) => Some(err),
+            LoadError::Asset(_, err) => Some(err),
+        }
+    }
+}
+
+/// Sent when a save finishes, with where it went or what went wrong
+#[derive(Event, Debug)]
+pub struct SaveResult(pub Result<PathBuf, SaveError>);
+
+/// Sent when the editor finishes loading a level, paths are relative to `assets/`
+#[derive(Event, Debug)]
+pub struct LoadResult(pub Result<PathBuf, LoadError>);
+
+#[derive(Component)]
+pub struct SaveTask(bevy::tasks::Task<Result<PathBuf, SaveError>>);
+
+pub fn poll_save_tasks(
+    mut tasks: Query<(Entity, &mut SaveTask)>,
+    mut save_results: EventWriter<SaveResult>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    use bevy::tasks::{block_on, futures_lite::future};
+
+    for (entity, mut task) in &mut tasks {
+        let Some(result) = block_on(future::poll_once(&mut task.0)) else {
+            continue;
+        };
+
+        match &result {
+            Ok(path) => {
                 info!(
                     "Succesfully serialized and saved scene to '{}'",
-                    target_path.display()
-                )
-            })
-            .detach();
+                    path.display()
+                );
+
+                // Otherwise the asset server keeps handing out the old version
+                if let Some(asset_path) = path.strip_prefix("assets/").ok().filter(|asset_path| {
+                    asset_server
+                        .get_handle::<DynamicScene>(*asset_path)
+                        .is_some()
+                }) {
+                    asset_server.reload(asset_path);
+                }
+            }
+            Err(err) => error!("Failed to save level: {err}"),
+        }
+
+        save_results.write(SaveResult(result));
+        commands.entity(entity).despawn();
     }
 }
 
 #[derive(Component)]
-pub struct TempSceneRoot;
+pub struct TempSceneRoot(Handle<DynamicScene>);
 
 // Only for use in editor
 // Just like the scene thing but extract all of the entities directly into the world
@@ -93,19 +285,113 @@ pub fn spawn_temp_scene(
 ) {
     let scene: Handle<DynamicScene> = asset_server.load(level_serialization_data.path.clone());
 
-    commands.spawn((TempSceneRoot, DynamicSceneRoot(scene)));
+    // Levels without their own settings shouldn't inherit the last level's
+    commands.insert_resource(crate::game::gravity::GravitySettings::default());
+    commands.insert_resource(crate::game::prediction::PredictionLimit::default());
+    commands.insert_resource(super::StartPoint::default());
+
+    commands.spawn(TempSceneRoot(scene));
+}
+
+// Writes levels into the world once they're loaded, exclusive so spawn errors can be reported
+pub fn spawn_loaded_temp_scenes(world: &mut World) {
+    let loaded: Vec<(Entity, Handle<DynamicScene>)> = world
+        .query::<(Entity, &TempSceneRoot)>()
+        .iter(world)
+        .filter(|(_, TempSceneRoot(handle))| {
+            world.resource::<Assets<DynamicScene>>().contains(handle)
+        })
+        .map(|(entity, TempSceneRoot(handle))| (entity, handle.clone()))
+        .collect();
+
+    for (root, handle) in loaded {
+        world.despawn(root);
+
+        let path = handle
+            .path()
+            .map(|path| path.path().to_path_buf())
+            .unwrap_or_default();
+
+        let mut entity_map = EntityHashMap::default();
+
+        let result = world.resource_scope(|world, scenes: Mut<Assets<DynamicScene>>| match scenes
+            .get(&handle)
+        {
+            Some(scene) => scene.write_to_world(world, &mut entity_map),
+            None => Ok(()),
+        });
+
+        let result = match result {
+            Ok(()) => Ok(path),
+            Err(err) => {
+                // Don't leave half a level lying around
+                for &entity in entity_map.values() {
+                    if let Ok(entity) = world.get_entity_mut(entity) {
+                        entity.despawn();
+                    }
+                }
+
+                let err = LoadError::Spawn(path, err);
+
+                error!("Failed to load level: {err}");
+
+                Err(err)
+            }
+        };
+
+        world.send_event(LoadResult(result));
+    }
 }
 
-pub fn free_temp_scene_children(
-    temp_scene: Single<Entity, With<TempSceneRoot>>,
-    children: Query<Entity, (With<ChildOf>, With<GameSerializable>)>,
+// The scene never gets loaded if its level fails to load, so clean up here instead
+pub fn report_temp_scene_failures(
+    mut failures: EventReader<bevy::asset::AssetLoadFailedEvent<DynamicScene>>,
+    temp_scenes: Query<(Entity, &TempSceneRoot)>,
+    mut load_results: EventWriter<LoadResult>,
     mut commands: Commands,
 ) {
-    for child in children {
-        commands.entity(child).remove::<ChildOf>();
+    use bevy::asset::{AssetLoadError, io::AssetReaderError};
+
+    for failure in failures.read() {
+        let Some((entity, _)) = temp_scenes
+            .iter()
+            .find(|(_, TempSceneRoot(handle))| handle.id() == failure.id)
+        else {
+            continue;
+        };
+
+        let path = failure.path.path().to_path_buf();
+
+        let err = match &failure.error {
+            AssetLoadError::AssetReaderError(AssetReaderError::NotFound(_)) => {
+                LoadError::NotFound(path)
+            }
+            // The asset server only hands back the loader's error as text, reading it again gets the real one
+            err => match super::game::read_level(
+                std::path::Path::new("assets").join(&path),
+                &serializable_components_type_registry(),
+            ) {
+                Err(read_err) => LoadError::from_read(path, read_err),
+                Ok(_) => LoadError::Asset(path, err.clone()),
+            },
+        };
+
+        error!("Failed to load level: {err}");
+
+        load_results.write(LoadResult(Err(err)));
+        commands.entity(entity).despawn();
     }
+}
 
-    commands.entity(temp_scene.into_inner()).despawn();
+// Only for use in editor
+pub fn apply_load_event(
+    mut events: EventReader<super::LoadEvent>,
+    mut level_serialization_data: ResMut<super::LevelSerializationData>,
+) {
+    // Only the latest one matters if there were a few
+    if let Some(event) = events.read().last() {
+        level_serialization_data.path = event.path.clone();
+    }
 }
 
 pub fn remove_level_entities(
@@ -122,11 +408,3 @@ pub fn remove_level_entities(
         }
     });
 }
-
-fn serializable_components_type_registry() -> TypeRegistry {
-    let mut registry = TypeRegistry::new();
-
-    registry.register::<(InternalSerializableTypes, ExternalSerializableTypes)>();
-
-    registry
-}
@@ -1,5 +1,5 @@
 use super::ActiveLevel;
-use bevy::prelude::*;
+use bevy::{prelude::*, reflect::TypeRegistry};
 
 pub fn load_active_level(
     level_serialization_data: Res<super::LevelSerializationData>,
@@ -11,6 +11,11 @@ pub fn load_active_level(
         level_serialization_data.path
     );
 
+    // Levels without their own settings shouldn't inherit the last level's
+    commands.insert_resource(crate::game::gravity::GravitySettings::default());
+    commands.insert_resource(crate::game::prediction::PredictionLimit::default());
+    commands.insert_resource(super::StartPoint::default());
+
     commands.spawn((
         DynamicSceneRoot(asset_server.load(level_serialization_data.path.clone())),
         ActiveLevel,
@@ -23,3 +28,106 @@ pub fn remove_active_level(
 ) {
     commands.entity(active_level.into_inner()).despawn();
 }
+
+/// Reads a level straight from disk, without going through the [AssetServer]
+pub fn read_level(
+    path: impl AsRef<std::path::Path>,
+    type_registry: &TypeRegistry,
+) -> Result<DynamicScene, LevelReadError> {
+    let source = std::fs::read_to_string(path)?;
+
+    deserialize_level(&source, type_registry)
+}
+
+/// Migrates a level to the current [super::migration::LevelFormat] and deserializes it
+pub fn deserialize_level(
+    source: &str,
+    type_registry: &TypeRegistry,
+) -> Result<DynamicScene, LevelReadError> {
+    use bevy::scene::{ron, serde::SceneDeserializer};
+    use serde::de::DeserializeSeed;
+
+    let source = super::migration::migrate_level(source);
+
+    if let Some(type_path) = unregistered_type(&source, type_registry) {
+        return Err(LevelReadError::UnknownType(type_path));
+    }
+
+    let mut deserializer = ron::de::Deserializer::from_str(&source)?;
+
+    let mut scene = SceneDeserializer { type_registry }
+        .deserialize(&mut deserializer)
+        .map_err(|err| deserializer.span_error(err))?;
+
+    super::migration::strip_level_format(&mut scene);
+
+    Ok(scene)
+}
+
+// The first type in the level that isn't in `type_registry`, checked up front so it gets its own error
+fn unregistered_type(source: &str, type_registry: &TypeRegistry) -> Option<String> {
+    use serde::{Deserialize, de::IgnoredAny};
+    use std::collections::HashMap;
+
+    #[derive(Deserialize)]
+    struct Types {
+        #[serde(default)]
+        resources: HashMap<String, IgnoredAny>,
+        #[serde(default)]
+        entities: HashMap<u64, EntityTypes>,
+    }
+
+    #[derive(Deserialize)]
+    struct EntityTypes {
+        #[serde(default)]
+        components: HashMap<String, IgnoredAny>,
+    }
+
+    // Syntax errors are left for the real deserializer to report
+    let types = bevy::scene::ron::from_str::<Types>(source).ok()?;
+
+    types
+        .resources
+        .into_keys()
+        .chain(
+            types
+                .entities
+                .into_values()
+                .flat_map(|entity| entity.components.into_keys()),
+        )
+        .find(|type_path| type_registry.get_with_type_path(type_path).is_none())
+}
+
+#[derive(Debug)]
+pub enum LevelReadError {
+    Io(std::io::Error),
+    Ron(bevy::scene::ron::error::SpannedError),
+    // A type in the level isn't registered, from a newer version or a component that was removed
+    UnknownType(String),
+}
+
+impl std::fmt::Display for LevelReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelReadError::Io(err) => write!(f, "Failed to read level file: {err}"),
+            LevelReadError::Ron(err) => write!(f, "Failed to parse level: {err}"),
+            LevelReadError::UnknownType(type_path) => {
+                write!(f, "'{type_path}' isn't a type levels can have")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelReadError {}
+
+impl From<std::io::Error> for LevelReadError {
+    fn from(value: std::io::Error) -> Self {
+        LevelReadError::Io(value)
+    }
+}
+
+impl From<bevy::scene::ron::error::SpannedError> for LevelReadError {
+    fn from(value: bevy::scene::ron::error::SpannedError) -> Self {
+        LevelReadError::Ron(value)
+    }
+}
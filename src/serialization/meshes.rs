@@ -3,7 +3,7 @@ use std::path::PathBuf;
 
 use super::zones::SerializableZoneMeshBuilder;
 
-#[derive(Component, Reflect)]
+#[derive(Component, Reflect, Clone)]
 #[reflect(Component)]
 pub enum SerializableMesh {
     Sprite {
@@ -59,6 +59,23 @@ impl SerializableMesh {
     pub fn zone<T: Into<super::zones::SerializableZone>>(zone: T) -> Self {
         SerializableMesh::Zone { zone: zone.into() }
     }
+
+    pub fn as_zone(&self) -> Option<&super::zones::SerializableZone> {
+        match self {
+            SerializableMesh::Zone { zone } => Some(zone),
+            _ => None,
+        }
+    }
+
+    /// Only circles have a radius, everything else gives none
+    pub fn circle_radius(&self) -> Option<f32> {
+        match self {
+            SerializableMesh::Primitive {
+                shape: SerializableMeshPrimitives::Circle(circle),
+            } => Some(circle.radius),
+            _ => None,
+        }
+    }
 }
 
 // NOTE: Directly using meshes causes deserialization to fail because of a divide by zero
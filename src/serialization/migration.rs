@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+    reflect::{TypePath, TypeRegistryArc},
+};
+use serde::Deserialize;
+
+use super::game::{LevelReadError, deserialize_level};
+
+/// Bump this and add a step to [MIGRATIONS] whenever a saved type moves or changes shape
+pub const LEVEL_FORMAT_VERSION: u32 = 1;
+
+/// Saved in every level so old files can be brought up to date when they're loaded
+///
+/// Levels from before this existed don't have it and count as version 0
+#[derive(Resource, Reflect, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[reflect(Resource)]
+pub struct LevelFormat {
+    pub version: u32,
+}
+
+impl Default for LevelFormat {
+    fn default() -> Self {
+        LevelFormat {
+            version: LEVEL_FORMAT_VERSION,
+        }
+    }
+}
+
+// Step `n` takes a level from version `n` to `n + 1`
+const MIGRATIONS: &[fn(&str) -> String] = &[rename_pre_module_type_paths];
+
+// Types that got moved into their own modules before levels were versioned
+const RENAMED_TYPES: &[(&str, &str)] = &[
+    (
+        "bevy_orbit::game::GameTrigger",
+        "bevy_orbit::game::trigger::GameTrigger",
+    ),
+    (
+        "bevy_orbit::game::KillOnCollision",
+        "bevy_orbit::game::death::KillOnCollision",
+    ),
+    (
+        "bevy_orbit::gravity::Gravity",
+        "bevy_orbit::game::gravity::Gravity",
+    ),
+    (
+        "bevy_orbit::gravity::GravityLayers",
+        "bevy_orbit::game::gravity::GravityLayers",
+    ),
+    (
+        "bevy_orbit::LevelObject",
+        "bevy_orbit::serialization::LevelObject",
+    ),
+    (
+        "bevy_orbit::serialization::SerializableCollider",
+        "bevy_orbit::serialization::colliders::SerializableCollider",
+    ),
+    (
+        "bevy_orbit::serialization::SerializableMesh",
+        "bevy_orbit::serialization::meshes::SerializableMesh",
+    ),
+    (
+        "bevy_orbit::serialization::SerilializableMeshMaterial",
+        "bevy_orbit::serialization::materials::SerilializableMeshMaterial",
+    ),
+];
+
+fn rename_pre_module_type_paths(source: &str) -> String {
+    // Matching the quotes too so a path is never mistaken for the start of a longer one
+    RENAMED_TYPES
+        .iter()
+        .fold(source.to_string(), |source, (old, new)| {
+            source.replace(&format!("\"{old}\""), &format!("\"{new}\""))
+        })
+}
+
+/// Reads the [LevelFormat] out of a level without deserializing the rest of it
+pub fn level_version(source: &str) -> u32 {
+    #[derive(Deserialize)]
+    struct Header {
+        #[serde(default)]
+        resources: HashMap<String, ron::Value>,
+    }
+
+    ron::from_str::<Header>(source)
+        .ok()
+        .and_then(|header| header.resources.get(LevelFormat::type_path()).cloned())
+        .and_then(|format| format.into_rust::<LevelFormat>().ok())
+        .map_or(0, |format| format.version)
+}
+
+/// Runs every migration step the level is missing, the result is a current level
+pub fn migrate_level(source: &str) -> String {
+    let version = level_version(source);
+
+    if version > LEVEL_FORMAT_VERSION {
+        warn!(
+            "Level is format version {version} but only {LEVEL_FORMAT_VERSION} is known, loading it anyway"
+        );
+    }
+
+    MIGRATIONS
+        .iter()
+        .skip(version as usize)
+        .fold(source.to_string(), |source, migration| migration(&source))
+}
+
+/// Drops the saved [LevelFormat] so loading a level never rolls back the world's one
+pub fn strip_level_format(scene: &mut DynamicScene) {
+    scene.resources.retain(|resource| {
+        resource
+            .get_represented_type_info()
+            .is_none_or(|info| info.type_path() != LevelFormat::type_path())
+    });
+}
+
+/// Loads `.level.ron` files, and `.scn.ron` ones saved before levels had their own extension
+///
+/// Every level goes through [migrate_level] on the way in. Bevy's scene loader also wants
+/// `.scn.ron`, the last loader added for an extension wins so this has to go in after
+/// [bevy::scene::ScenePlugin]
+pub struct LevelLoader {
+    type_registry: TypeRegistryArc,
+}
+
+impl FromWorld for LevelLoader {
+    fn from_world(world: &mut World) -> Self {
+        LevelLoader {
+            type_registry: world.resource::<AppTypeRegistry>().0.clone(),
+        }
+    }
+}
+
+impl AssetLoader for LevelLoader {
+    type Asset = DynamicScene;
+    type Settings = ();
+    type Error = LevelReadError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<DynamicScene, LevelReadError> {
+        let mut bytes = vec![];
+
+        reader.read_to_end(&mut bytes).await?;
+
+        let source = String::from_utf8(bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+        deserialize_level(&source, &self.type_registry.read())
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron", "scn.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::scene::{ScenePlugin, ron, serde::SceneDeserializer};
+    use serde::de::DeserializeSeed;
+
+    use super::*;
+    use crate::serialization::serializable_components_type_registry;
+
+    const TEST_LEVELS: &[&str] = &["level2", "level3", "level4"];
+
+    fn read_test_level(name: &str) -> String {
+        let path = format!(
+            "{}/assets/test_levels/{name}.level.ron",
+            env!("CARGO_MANIFEST_DIR")
+        );
+
+        std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("Failed to read {path}: {err}"))
+    }
+
+    fn deserialize(source: &str) -> DynamicScene {
+        let registry = serializable_components_type_registry();
+        let mut deserializer = ron::de::Deserializer::from_str(source).unwrap();
+
+        SceneDeserializer {
+            type_registry: &registry,
+        }
+        .deserialize(&mut deserializer)
+        .unwrap_or_else(|err| panic!("{}", deserializer.span_error(err)))
+    }
+
+    // Puts the level back the way it would have been saved before the types were moved
+    fn with_old_type_paths(source: &str) -> String {
+        RENAMED_TYPES
+            .iter()
+            .fold(source.to_string(), |source, (old, new)| {
+                source.replace(&format!("\"{new}\""), &format!("\"{old}\""))
+            })
+    }
+
+    #[test]
+    fn test_levels_migrate_and_deserialize() {
+        for name in TEST_LEVELS {
+            let source = read_test_level(name);
+
+            let scene = deserialize(&migrate_level(&source));
+
+            assert!(!scene.entities.is_empty(), "{name} has no entities");
+        }
+    }
+
+    #[test]
+    fn old_type_paths_are_renamed() {
+        for name in TEST_LEVELS {
+            let source = read_test_level(name);
+            let old = with_old_type_paths(&source);
+
+            assert_eq!(level_version(&old), 0);
+            // Some of the test levels are old enough to need migrating themselves
+            assert_eq!(
+                migrate_level(&old),
+                migrate_level(&source),
+                "{name} didn't migrate back"
+            );
+
+            deserialize(&migrate_level(&old));
+        }
+    }
+
+    #[test]
+    fn old_scn_ron_files_go_through_the_level_loader() {
+        let dir = std::env::temp_dir().join(format!("bevy_orbit_scn_ron_{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("old.scn.ron"),
+            with_old_type_paths(&read_test_level("level2")),
+        )
+        .unwrap();
+
+        let mut app = App::new();
+
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: dir.to_string_lossy().into_owned(),
+                ..default()
+            },
+            ScenePlugin,
+            crate::serialization::SerializeableTypeRegistrationPlugin,
+        ))
+        .init_asset_loader::<LevelLoader>();
+
+        let handle: Handle<DynamicScene> =
+            app.world().resource::<AssetServer>().load("old.scn.ron");
+
+        // Loading happens on another thread
+        for _ in 0..1000 {
+            app.update();
+
+            let asset_server = app.world().resource::<AssetServer>();
+
+            if asset_server.is_loaded(&handle) || asset_server.load_state(&handle).is_failed() {
+                break;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let scene = app
+            .world()
+            .resource::<Assets<DynamicScene>>()
+            .get(&handle)
+            .expect("old.scn.ron didn't load");
+
+        let type_paths: Vec<&str> = scene
+            .entities
+            .iter()
+            .flat_map(|entity| &entity.components)
+            .filter_map(|component| component.get_represented_type_info())
+            .map(|info| info.type_path())
+            .collect();
+
+        for (old, _) in RENAMED_TYPES {
+            assert!(!type_paths.contains(old), "{old} wasn't renamed");
+        }
+
+        assert!(type_paths.contains(&"bevy_orbit::game::trigger::GameTrigger"));
+    }
+
+    #[test]
+    fn current_version_is_unchanged() {
+        // Old looking paths in a current level are left alone, they aren't that type anymore
+        let source = format!(
+            r#"(
+  resources: {{
+    "{}": (
+      version: {LEVEL_FORMAT_VERSION},
+    ),
+  }},
+  entities: {{
+    1: (
+      components: {{
+        "bevy_orbit::game::GameTrigger": (),
+      }},
+    ),
+  }},
+)"#,
+            LevelFormat::type_path()
+        );
+
+        assert_eq!(level_version(&source), LEVEL_FORMAT_VERSION);
+        assert_eq!(migrate_level(&source), source);
+    }
+}
@@ -1,54 +1,89 @@
+pub mod backups;
 pub mod colliders;
 pub mod editor;
 pub mod game;
 pub mod materials;
 pub mod meshes;
+pub mod migration;
+pub mod packs;
+pub mod progress;
+pub mod triangulation;
 pub mod zones;
 
-use bevy::prelude::*;
+use bevy::{prelude::*, reflect::TypeRegistry};
 use std::path::PathBuf;
 
 pub struct SerializationPlugin;
 
 impl Plugin for SerializationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((SerializeableTypeRegistrationPlugin, zones::ZonePlugin))
-            .add_event::<SaveEvent>()
-            .insert_resource(LevelSerializationData::new("test_levels/level2.scn.ron"))
-            .init_resource::<StartPoint>()
-            .add_systems(
-                Update,
-                (
-                    colliders::initialize_colliders,
-                    meshes::initialize_meshes,
-                    materials::initialize_mesh_materials,
-                    editor::serialize_objects,
-                    editor::free_temp_scene_children,
-                    draw_start_point.run_if(crate::helper::app_state_is(crate::AppState::Editor)),
-                ),
-            );
+        app.add_plugins((
+            SerializeableTypeRegistrationPlugin,
+            zones::ZonePlugin,
+            packs::PackPlugin,
+            progress::ProgressPlugin,
+            backups::BackupPlugin,
+        ))
+        .init_asset_loader::<migration::LevelLoader>()
+        .init_resource::<migration::LevelFormat>()
+        .add_event::<SaveEvent>()
+        .add_event::<LoadEvent>()
+        .add_event::<editor::SaveResult>()
+        .add_event::<editor::LoadResult>()
+        .insert_resource(LevelSerializationData::new("test_levels/level2.level.ron"))
+        .init_resource::<StartPoint>()
+        .add_systems(
+            Update,
+            (
+                colliders::initialize_colliders,
+                meshes::initialize_meshes,
+                materials::initialize_mesh_materials,
+                editor::serialize_objects,
+                editor::poll_save_tasks,
+                editor::spawn_loaded_temp_scenes,
+                editor::report_temp_scene_failures,
+                draw_start_point.run_if(crate::helper::app_state_is(crate::AppState::Editor)),
+            ),
+        );
     }
 }
 
+// Reflect only goes up to 12 element tuples, so these are split up
 type InternalSerializableTypes = (
-    crate::game::gravity::Gravity,
-    crate::game::gravity::GravityLayers,
-    crate::game::trigger::GameTrigger,
-    crate::game::death::KillOnCollision,
-    crate::game::launch::DynamicObject,
-    colliders::SerializableCollider,
-    meshes::SerializableMesh,
-    meshes::SerializableMeshPrimitives,
-    materials::SerilializableMeshMaterial,
-    GameSerializable,
-    StartPoint,
-    LevelObject,
+    (
+        crate::game::gravity::Gravity,
+        crate::game::gravity::GravityLayers,
+        crate::game::gravity::GravitySettings,
+        crate::game::gravity::GravityFalloff,
+        crate::game::gravity::sources::GravitySource,
+        crate::game::gravity::sources::GravityShape,
+    ),
+    (
+        crate::game::trigger::GameTrigger,
+        crate::game::death::KillOnCollision,
+        crate::game::launch::DynamicObject,
+        crate::game::rails::OrbitRail,
+        crate::game::rails::RailPath,
+        crate::game::collision::CollisionResponse,
+        crate::game::prediction::PredictionLimit,
+    ),
+    (
+        colliders::SerializableCollider,
+        meshes::SerializableMesh,
+        meshes::SerializableMeshPrimitives,
+        materials::SerilializableMeshMaterial,
+        GameSerializable,
+        StartPoint,
+        LevelObject,
+        migration::LevelFormat,
+    ),
 );
 
 type ExternalSerializableTypes = (
     avian2d::prelude::CollisionLayers,
     avian2d::prelude::Mass,
     avian2d::prelude::RigidBody,
+    avian2d::prelude::LinearVelocity,
     Transform,
 );
 
@@ -62,6 +97,15 @@ impl Plugin for SerializeableTypeRegistrationPlugin {
     }
 }
 
+// A registry with only the level types in it, nothing outside of it gets saved
+pub fn serializable_components_type_registry() -> TypeRegistry {
+    let mut registry = TypeRegistry::new();
+
+    registry.register::<(InternalSerializableTypes, ExternalSerializableTypes)>();
+
+    registry
+}
+
 #[derive(Component, Reflect)]
 #[reflect(Component)]
 pub struct LevelObject;
@@ -137,6 +181,18 @@ impl SaveEvent {
     }
 }
 
+// Replaces whatever is in the editor with the level at the path
+#[derive(Event)]
+pub struct LoadEvent {
+    path: PathBuf,
+}
+
+impl LoadEvent {
+    pub fn new<U: Into<PathBuf>>(path: U) -> Self {
+        LoadEvent { path: path.into() }
+    }
+}
+
 // Instead of storing an option I could just make it either exist or not...
 #[derive(Deref, DerefMut, Reflect, Resource, Debug)]
 #[reflect(Resource)]
@@ -0,0 +1,255 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game::completion::{AdvanceLevel, LevelComplete};
+
+// Relative to the working directory, like the editor's save path
+const DEFAULT_PACK_PATH: &str = "assets/packs/test_levels.pack.ron";
+
+pub struct PackPlugin;
+
+impl Plugin for PackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_default_pack)
+            .add_systems(Update, mark_level_completed);
+    }
+}
+
+/// An ordered set of levels, read from a `.pack.ron` manifest
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LevelPack {
+    pub name: String,
+    pub levels: Vec<PackLevel>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackLevel {
+    pub name: String,
+    // Relative to `assets/`, same as [super::LevelSerializationData]
+    pub path: PathBuf,
+    #[serde(default)]
+    pub unlock: UnlockRequirement,
+    // How many launches a good run takes
+    #[serde(default)]
+    pub par: Option<u32>,
+}
+
+/// What has to be done before a level in a pack can be played
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub enum UnlockRequirement {
+    #[default]
+    Always,
+    // The level right before this one
+    Previous,
+    // Every level with one of these names
+    Levels(Vec<String>),
+    // Any this many levels in the pack
+    Completed(usize),
+}
+
+impl LevelPack {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+
+        let source = std::fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read pack '{}': {err}", path.display()))?;
+
+        ron::from_str(&source)
+            .map_err(|err| format!("Failed to parse pack '{}': {err}", path.display()))
+    }
+}
+
+/// The pack being played and where in it the player is
+#[derive(Resource, Debug)]
+pub struct CurrentPack {
+    pub pack: LevelPack,
+    pub index: usize,
+    // Paths of the levels beaten so far
+    pub completed: HashSet<PathBuf>,
+}
+
+impl CurrentPack {
+    pub fn new(pack: LevelPack) -> Self {
+        CurrentPack {
+            pack,
+            index: 0,
+            completed: HashSet::new(),
+        }
+    }
+
+    pub fn current(&self) -> Option<&PackLevel> {
+        self.pack.levels.get(self.index)
+    }
+
+    pub fn is_completed(&self, index: usize) -> bool {
+        self.pack
+            .levels
+            .get(index)
+            .is_some_and(|level| self.completed.contains(&level.path))
+    }
+
+    pub fn is_unlocked(&self, index: usize) -> bool {
+        let Some(level) = self.pack.levels.get(index) else {
+            return false;
+        };
+
+        match &level.unlock {
+            UnlockRequirement::Always => true,
+            UnlockRequirement::Previous => index == 0 || self.is_completed(index - 1),
+            UnlockRequirement::Levels(names) => names.iter().all(|name| {
+                self.pack
+                    .levels
+                    .iter()
+                    .position(|level| &level.name == name)
+                    .is_some_and(|index| self.is_completed(index))
+            }),
+            UnlockRequirement::Completed(count) => {
+                (0..self.pack.levels.len())
+                    .filter(|&index| self.is_completed(index))
+                    .count()
+                    >= *count
+            }
+        }
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.is_unlocked(self.index + 1)
+    }
+
+    // Levels can unlock out of order, so the one before might not be playable yet
+    pub fn has_previous(&self) -> bool {
+        self.index > 0 && self.is_unlocked(self.index - 1)
+    }
+
+    /// Moves to the next level if it's unlocked, returns whether it moved
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> bool {
+        let has_next = self.has_next();
+
+        if has_next {
+            self.index += 1;
+        }
+
+        has_next
+    }
+
+    /// Moves to the previous level if it's unlocked, returns whether it moved
+    pub fn previous(&mut self) -> bool {
+        let has_previous = self.has_previous();
+
+        if has_previous {
+            self.index -= 1;
+        }
+
+        has_previous
+    }
+}
+
+fn load_default_pack(
+    mut commands: Commands,
+    mut serialization_data: ResMut<super::LevelSerializationData>,
+) {
+    match LevelPack::load(DEFAULT_PACK_PATH) {
+        Ok(pack) => {
+            info!("Loaded level pack '{}'", pack.name);
+
+            let pack = CurrentPack::new(pack);
+
+            if let Some(level) = pack.current() {
+                serialization_data.path = level.path.clone();
+            }
+
+            commands.insert_resource(pack);
+        }
+        Err(err) => warn!("{err}, using the default level"),
+    }
+}
+
+fn mark_level_completed(
+    mut level_complete: EventReader<LevelComplete>,
+    pack: Option<ResMut<CurrentPack>>,
+) {
+    let Some(mut pack) = pack else {
+        level_complete.clear();
+        return;
+    };
+
+    for _ in level_complete.read() {
+        if let Some(path) = pack.current().map(|level| level.path.clone()) {
+            pack.completed.insert(path);
+        }
+    }
+}
+
+/// Points [super::LevelSerializationData] at the level the [AdvanceLevel] events ask for
+///
+/// Without a pack this does nothing and the same level just gets reloaded
+pub fn navigate_pack(
+    mut advance_level: EventReader<AdvanceLevel>,
+    pack: Option<ResMut<CurrentPack>>,
+    mut serialization_data: ResMut<super::LevelSerializationData>,
+) {
+    let Some(mut pack) = pack else {
+        advance_level.clear();
+        return;
+    };
+
+    for event in advance_level.read() {
+        let moved = match event {
+            AdvanceLevel::Next => pack.next(),
+            AdvanceLevel::Previous => pack.previous(),
+        };
+
+        if !moved {
+            info!("No level to go to, staying on the current one");
+        }
+    }
+
+    if let Some(level) = pack.current() {
+        serialization_data.path = level.path.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(name: &str, unlock: UnlockRequirement) -> PackLevel {
+        PackLevel {
+            name: name.to_string(),
+            path: PathBuf::from(format!("{name}.level.ron")),
+            unlock,
+            par: None,
+        }
+    }
+
+    #[test]
+    fn previous_checks_unlocks_too() {
+        let mut pack = CurrentPack::new(LevelPack {
+            name: "Test".to_string(),
+            levels: vec![
+                level("intro", UnlockRequirement::Always),
+                level(
+                    "finale",
+                    UnlockRequirement::Levels(vec!["intro".to_string()]),
+                ),
+                level("bonus", UnlockRequirement::Always),
+            ],
+        });
+
+        pack.index = 2;
+
+        assert!(!pack.previous());
+        assert_eq!(pack.index, 2);
+
+        pack.completed.insert(PathBuf::from("intro.level.ron"));
+
+        assert!(pack.previous());
+        assert_eq!(pack.index, 1);
+        assert!(pack.previous());
+        assert!(!pack.previous());
+        assert_eq!(pack.index, 0);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::packs::CurrentPack;
+use crate::game::{
+    GameState,
+    completion::{AdvanceLevel, LevelComplete},
+};
+
+/// Set this to use a different progress file, mostly for tests
+pub const PROGRESS_PATH_VAR: &str = "BEVY_ORBIT_PROGRESS";
+
+pub struct ProgressPlugin;
+
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut App) {
+        // Loaded right away so the pack can check unlocks on startup
+        app.insert_resource(PlayerProgress::load(&progress_path()))
+            .init_resource::<LevelRun>()
+            // The pack is only there once its startup system's commands have gone through
+            .add_systems(PostStartup, resume_pack)
+            .add_systems(
+                Update,
+                (
+                    reset_level_run
+                        .run_if(on_event::<AdvanceLevel>.or(resource_changed::<crate::AppState>)),
+                    count_attempts.run_if(resource_changed::<GameState>),
+                    record_completion.run_if(on_event::<LevelComplete>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Where the progress file lives, [PROGRESS_PATH_VAR] or the platform's config directory
+pub fn progress_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(PROGRESS_PATH_VAR) {
+        return PathBuf::from(path);
+    }
+
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+        })
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    // Fall back to the working directory if there's nowhere better
+    config_dir
+        .unwrap_or_default()
+        .join("bevy-orbit")
+        .join("progress.ron")
+}
+
+/// Everything the player has done, saved between sessions
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct PlayerProgress {
+    // Keyed by level path, relative to `assets/`
+    pub levels: BTreeMap<PathBuf, LevelProgress>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct LevelProgress {
+    pub completed: bool,
+    pub fewest_attempts: Option<u32>,
+    // Seconds from the winning launch to the last trigger
+    pub best_time: Option<f32>,
+    pub stars: u32,
+}
+
+impl PlayerProgress {
+    /// Missing or broken files give empty progress instead of an error
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(source) => ron::from_str(&source).unwrap_or_else(|err| {
+                warn!(
+                    "Progress file '{}' is corrupt, starting fresh: {err}",
+                    path.display()
+                );
+                PlayerProgress::default()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("No progress file at '{}', starting fresh", path.display());
+                PlayerProgress::default()
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to read progress file '{}', starting fresh: {err}",
+                    path.display()
+                );
+                PlayerProgress::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let serialized = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+
+        // The config directory won't exist on a fresh install
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+
+        crate::helper::write_atomic(path, serialized.as_bytes()).map_err(|err| err.to_string())
+    }
+
+    pub fn level(&self, path: &Path) -> Option<&LevelProgress> {
+        self.levels.get(path)
+    }
+
+    pub fn is_completed(&self, path: &Path) -> bool {
+        self.level(path).is_some_and(|level| level.completed)
+    }
+
+    /// Merges a finished run into the level's records, keeping the best of each
+    pub fn record(&mut self, path: PathBuf, attempts: u32, time: f32, stars: u32) {
+        let level = self.levels.entry(path).or_default();
+
+        level.completed = true;
+        level.fewest_attempts = Some(
+            level
+                .fewest_attempts
+                .map_or(attempts, |best| best.min(attempts)),
+        );
+        level.best_time = Some(level.best_time.map_or(time, |best| best.min(time)));
+        level.stars = level.stars.max(stars);
+    }
+}
+
+/// Three stars at or under par, two within double par, one otherwise
+///
+/// Levels without a par count a single launch as par
+pub fn stars_for(attempts: u32, par: Option<u32>) -> u32 {
+    let par = par.unwrap_or(1).max(1);
+
+    if attempts <= par {
+        3
+    } else if attempts <= par * 2 {
+        2
+    } else {
+        1
+    }
+}
+
+// The pack only knows about levels beaten this session, so it's caught up on startup
+fn resume_pack(
+    pack: Option<ResMut<CurrentPack>>,
+    progress: Res<PlayerProgress>,
+    mut serialization_data: ResMut<super::LevelSerializationData>,
+) {
+    let Some(mut pack) = pack else {
+        return;
+    };
+
+    let beaten: Vec<PathBuf> = pack
+        .pack
+        .levels
+        .iter()
+        .map(|level| level.path.clone())
+        .filter(|path| progress.is_completed(path))
+        .collect();
+
+    pack.completed.extend(beaten);
+
+    // Pick up where the player left off
+    while pack.is_completed(pack.index) && pack.next() {}
+
+    if let Some(level) = pack.current() {
+        serialization_data.path = level.path.clone();
+    }
+}
+
+/// The run through the level currently being played
+#[derive(Resource, Default, Debug)]
+pub struct LevelRun {
+    pub attempts: u32,
+    // Elapsed seconds when the latest launch happened
+    launched_at: Option<f32>,
+    // Set once the level is completed (attempts, time, stars)
+    pub result: Option<(u32, f32, u32)>,
+}
+
+fn reset_level_run(mut run: ResMut<LevelRun>) {
+    *run = LevelRun::default();
+}
+
+fn count_attempts(game_state: Res<GameState>, mut run: ResMut<LevelRun>, time: Res<Time>) {
+    if *game_state == GameState::Launched {
+        run.attempts += 1;
+        run.launched_at = Some(time.elapsed_secs());
+    }
+}
+
+fn record_completion(
+    mut level_complete: EventReader<LevelComplete>,
+    mut run: ResMut<LevelRun>,
+    mut progress: ResMut<PlayerProgress>,
+    serialization_data: Res<super::LevelSerializationData>,
+    pack: Option<Res<CurrentPack>>,
+    time: Res<Time>,
+) {
+    level_complete.clear();
+
+    let attempts = run.attempts.max(1);
+    let flight_time = time.elapsed_secs() - run.launched_at.unwrap_or(time.elapsed_secs());
+
+    let par = pack
+        .as_ref()
+        .and_then(|pack| pack.current())
+        .filter(|level| level.path == serialization_data.path)
+        .and_then(|level| level.par);
+
+    let stars = stars_for(attempts, par);
+
+    run.result = Some((attempts, flight_time, stars));
+
+    progress.record(
+        serialization_data.path.clone(),
+        attempts,
+        flight_time,
+        stars,
+    );
+
+    let path = progress_path();
+
+    match progress.save(&path) {
+        Ok(()) => info!("Saved progress to '{}'", path.display()),
+        Err(err) => error!("Failed to save progress to '{}': {err}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "bevy_orbit_progress_{name}_{}.ron",
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn missing_file_starts_fresh() {
+        let progress = PlayerProgress::load(&temp_path("missing"));
+
+        assert!(progress.levels.is_empty());
+    }
+
+    #[test]
+    fn corrupt_file_starts_fresh() {
+        let path = temp_path("corrupt");
+
+        std::fs::write(&path, "(levels: {\"level.ron\": (completed: maybe").unwrap();
+
+        let progress = PlayerProgress::load(&path);
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(progress.levels.is_empty());
+    }
+
+    #[test]
+    fn record_keeps_the_best_run() {
+        let path = PathBuf::from("test_levels/level2.level.ron");
+        let mut progress = PlayerProgress::default();
+
+        progress.record(path.clone(), 4, 12.5, 2);
+        progress.record(path.clone(), 6, 8.0, 1);
+        progress.record(path.clone(), 2, 20.0, 3);
+
+        let level = progress.level(&path).unwrap();
+
+        assert!(level.completed);
+        assert_eq!(level.fewest_attempts, Some(2));
+        assert_eq!(level.best_time, Some(8.0));
+        assert_eq!(level.stars, 3);
+    }
+
+    #[test]
+    fn saves_where_the_variable_points() {
+        let path = temp_path("variable").join("progress.ron");
+
+        // SAFETY: nothing else in the tests reads or writes this variable
+        unsafe { std::env::set_var(PROGRESS_PATH_VAR, &path) };
+
+        assert_eq!(progress_path(), path);
+
+        let mut progress = PlayerProgress::default();
+        progress.record(PathBuf::from("level.level.ron"), 1, 3.0, 3);
+
+        // Creates the missing directory too
+        progress.save(&progress_path()).unwrap();
+
+        let loaded = PlayerProgress::load(&progress_path());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert!(loaded.is_completed(Path::new("level.level.ron")));
+        assert_eq!(
+            loaded
+                .level(Path::new("level.level.ron"))
+                .and_then(|level| level.best_time),
+            Some(3.0)
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+
+// Geometry for turning zone outlines into triangles, none of it cares which way the outline winds
+
+/// Positive when the points go counter-clockwise
+pub fn signed_area(points: &[Vec2]) -> f32 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Whether any two edges that aren't next to each other cross
+pub fn is_self_intersecting(points: &[Vec2]) -> bool {
+    let len = points.len();
+
+    let edge = |i: usize| (points[i], points[(i + 1) % len]);
+
+    (0..len).any(|i| {
+        (i + 2..len)
+            // The last edge shares a point with the first one
+            .filter(|&j| !(i == 0 && j == len - 1))
+            .any(|j| segments_cross(edge(i), edge(j)))
+    })
+}
+
+// Only counts proper crossings, touching at an end doesn't
+fn segments_cross((a, b): (Vec2, Vec2), (c, d): (Vec2, Vec2)) -> bool {
+    let side_c = (b - a).perp_dot(c - a);
+    let side_d = (b - a).perp_dot(d - a);
+    let side_a = (d - c).perp_dot(a - c);
+    let side_b = (d - c).perp_dot(b - c);
+
+    side_c * side_d < 0.0 && side_a * side_b < 0.0
+}
+
+/// Ear clipping, works for concave outlines but not ones that cross themselves
+///
+/// Triangles come out counter-clockwise, none if the outline can't be triangulated
+pub fn triangulate(points: &[Vec2]) -> Option<Vec<[u32; 3]>> {
+    // Ears can still be found on some of these, but the triangles end up flipped
+    if points.len() < 3 || is_self_intersecting(points) {
+        return None;
+    }
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+
+    if signed_area(points) < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+
+        let corner = |i: usize| {
+            (
+                remaining[(i + len - 1) % len],
+                remaining[i],
+                remaining[(i + 1) % len],
+            )
+        };
+
+        // No ear means the outline crosses itself or is all in a line
+        let ear = (0..len).find(|&i| is_ear(points, &remaining, corner(i)))?;
+
+        let (previous, current, next) = corner(ear);
+
+        triangles.push([previous as u32, current as u32, next as u32]);
+        remaining.remove(ear);
+    }
+
+    triangles.push([
+        remaining[0] as u32,
+        remaining[1] as u32,
+        remaining[2] as u32,
+    ]);
+
+    Some(triangles)
+}
+
+fn is_ear(points: &[Vec2], remaining: &[usize], (a, b, c): (usize, usize, usize)) -> bool {
+    let (point_a, point_b, point_c) = (points[a], points[b], points[c]);
+
+    // Corners that turn the wrong way (or not at all) can't be cut off
+    if (point_b - point_a).perp_dot(point_c - point_b) <= 0.0 {
+        return false;
+    }
+
+    remaining
+        .iter()
+        .filter(|&&i| i != a && i != b && i != c)
+        .all(|&i| !in_triangle(points[i], point_a, point_b, point_c))
+}
+
+// The triangle has to be counter-clockwise, points on the edge count as inside
+fn in_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    (b - a).perp_dot(point - a) >= 0.0
+        && (c - b).perp_dot(point - b) >= 0.0
+        && (a - c).perp_dot(point - c) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const L_SHAPE: [Vec2; 6] = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(1.0, 2.0),
+        Vec2::new(0.0, 2.0),
+    ];
+
+    fn triangle_area(points: &[Vec2], [a, b, c]: [u32; 3]) -> f32 {
+        signed_area(&[points[a as usize], points[b as usize], points[c as usize]])
+    }
+
+    // Every triangle counter-clockwise and together covering the whole outline
+    fn assert_covers(points: &[Vec2], triangles: &[[u32; 3]]) {
+        assert_eq!(triangles.len(), points.len() - 2);
+
+        for &triangle in triangles {
+            assert!(
+                triangle_area(points, triangle) >= 0.0,
+                "{triangle:?} is clockwise"
+            );
+        }
+
+        let total: f32 = triangles
+            .iter()
+            .map(|&triangle| triangle_area(points, triangle))
+            .sum();
+
+        assert!((total - signed_area(points).abs()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn concave_l_shape() {
+        let triangles = triangulate(&L_SHAPE).unwrap();
+
+        assert_covers(&L_SHAPE, &triangles);
+    }
+
+    #[test]
+    fn clockwise_input() {
+        let mut points = L_SHAPE.to_vec();
+        points.reverse();
+
+        assert!(signed_area(&points) < 0.0);
+
+        let triangles = triangulate(&points).unwrap();
+
+        assert_covers(&points, &triangles);
+    }
+
+    #[test]
+    fn collinear_vertices() {
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+
+        let triangles = triangulate(&points).unwrap();
+
+        assert_covers(&points, &triangles);
+    }
+
+    #[test]
+    fn self_intersecting_bowtie() {
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(0.0, 2.0),
+        ];
+
+        assert!(is_self_intersecting(&points));
+        assert!(!is_self_intersecting(&L_SHAPE));
+        assert_eq!(triangulate(&points), None);
+    }
+}
@@ -14,37 +14,40 @@ pub struct ZonePlugin;
 
 impl Plugin for ZonePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
+        app.init_resource::<ZoneColliderMode>()
+            .add_event::<ZoneWarning>()
+            .add_systems(
+                Update,
                 (
-                    zone_creation_input_handler.run_if(input_pressed(KeyCode::ControlLeft)),
-                    initialize_zone_builder.run_if(input_just_pressed(KeyCode::ControlLeft)),
-                    convert_zone_builders.run_if(input_just_released(KeyCode::ControlLeft)),
-                    zone_creation_outline_gizmos,
-                )
-                    .run_if(app_state_is(AppState::Editor)),
-                convert_zone_builders.run_if(app_state_is(AppState::Play)),
-            ),
-        );
+                    (
+                        zone_creation_input_handler.run_if(input_pressed(KeyCode::ControlLeft)),
+                        initialize_zone_builder.run_if(input_just_pressed(KeyCode::ControlLeft)),
+                        convert_zone_builders.run_if(input_just_released(KeyCode::ControlLeft)),
+                        zone_creation_outline_gizmos,
+                    )
+                        .run_if(app_state_is(AppState::Editor)),
+                    convert_zone_builders.run_if(app_state_is(AppState::Play)),
+                ),
+            );
     }
 }
 
-impl Into<ColliderConstructor> for SerializableZoneBuilder {
-    fn into(self) -> ColliderConstructor {
-        let mut indices: Vec<[u32; 3]> = vec![];
-
-        for i in 0..=self.0.indices.len() / 3 - 1 {
-            indices.push(self.0.indices[i * 3..i * 3 + 3].try_into().unwrap())
-        }
-
-        ColliderConstructor::Trimesh {
-            vertices: self.0.vertices,
-            indices,
-        }
-    }
+/// What kind of collider new zones get
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub enum ZoneColliderMode {
+    // Solid, matches the mesh exactly
+    #[default]
+    Trimesh,
+    // Just the outline, things can sit inside without touching it
+    Polyline,
+    // Solid, split into convex pieces which is cheaper for the physics than a trimesh
+    ConvexDecomposition,
 }
 
+/// Something's off with a zone's shape, the editor shows these
+#[derive(Event, Debug)]
+pub struct ZoneWarning(pub String);
+
 #[derive(Component, Default, Clone)]
 pub struct SerializableZoneBuilder(pub SerializableZone);
 
@@ -53,44 +56,23 @@ impl SerializableZoneBuilder {
         self.0.vertices.push(point);
     }
 
-    fn remove_last(&mut self) {
-        let len = self.0.vertices.len();
-
-        self.0.vertices.remove(len - 1);
-    }
-
-    fn get_center(&self) -> Vec2 {
-        let mut iterator = self.0.vertices.iter();
-
-        iterator.next(); // Ignore the first thing 
-
-        iterator.fold(Vec2::ZERO, |acc, pos| acc + pos) / (self.0.vertices.len() as f32 - 1.0)
+    // Edges snap by angle from the last point, the first point just snaps like anything else
+    fn snap_point(&self, point: Vec2, snapping: &crate::editor::snapping::Snapping) -> Vec2 {
+        match self.outline().last() {
+            Some(&last) => snapping.edge_end(last, point),
+            None => snapping.point(point),
+        }
     }
 
-    // Always draws lines to well.. the center (hence the naive)
-    // Am I figuring out how to correctly generate indices??? No!
-    // If you're sad that this doesn't work perfectly, too bad!
-    fn build_indicies_naive(mut self) -> (Self, Vec2) {
-        let center = self.get_center();
-
-        self.0.vertices[0] = center;
-
-        self.0
-            .vertices
-            .iter_mut()
-            .for_each(|vertice| *vertice -= center);
-
-        for i in 1..(self.0.vertices.len() - 1) as u32 {
-            self.0.indices.push(0);
-            self.0.indices.push(i);
-            self.0.indices.push(i + 1);
+    // Never removes the first vertex, it's only a placeholder for the center
+    fn remove_last(&mut self) {
+        if self.0.vertices.len() > 1 {
+            self.0.vertices.pop();
         }
+    }
 
-        self.0.indices.push(0);
-        self.0.indices.push(self.0.vertices.len() as u32 - 1);
-        self.0.indices.push(1);
-
-        (self, center)
+    fn outline(&self) -> &[Vec2] {
+        self.0.vertices.get(1..).unwrap_or_default()
     }
 }
 
@@ -136,15 +118,37 @@ impl Into<SerializableZone> for SerializableZoneBuilder {
 pub fn convert_zone_builders(
     mut commands: Commands,
     builders: Query<(Entity, &SerializableZoneBuilder)>,
+    collider_mode: Res<ZoneColliderMode>,
+    mut warnings: EventWriter<ZoneWarning>,
+    mut history: Option<ResMut<crate::editor::history::History>>,
 ) {
     builders.iter().for_each(|(entity, builder)| {
         let mut entity_commands = commands.entity(entity);
 
-        let (zone, center) = builder.clone().build_indicies_naive();
+        // The first vertex is the center, so this is fewer than three points around it
+        if builder.0.vertices.len() < 4 {
+            entity_commands.despawn();
+            return;
+        }
+
+        if let Some(history) = history.as_mut() {
+            history.record_spawn("Add zone", [entity]);
+        }
+
+        let (zone, center) = SerializableZone::from_outline(builder.outline());
+
+        // Still gets built, it just won't look or collide quite right
+        if zone.is_self_intersecting() {
+            warn!("Zone at {center} crosses over itself");
+            warnings.write(ZoneWarning(
+                "Zone outline crosses over itself, its shape may be wrong".to_string(),
+            ));
+        }
+
         entity_commands
             .insert((
                 super::meshes::SerializableMesh::zone(zone.clone()),
-                super::colliders::SerializableCollider::new(zone.clone().into()),
+                super::colliders::SerializableCollider::new(zone.collider(*collider_mode)),
                 Transform::from_translation(center.extend(-1.0)),
                 super::materials::SerilializableMeshMaterial::color(Color::srgba(
                     0.8, 0.1, 0.3, 0.3,
@@ -165,11 +169,14 @@ pub fn zone_creation_input_handler(
     keys: Res<ButtonInput<KeyCode>>,
     mouse: Res<ButtonInput<MouseButton>>,
     cursor_pos: Res<crate::cursor::CursorPosition>,
+    snapping: crate::editor::snapping::Snapping,
     mut _commands: Commands,
 ) {
     if let Some(cursor_pos) = **cursor_pos {
         if mouse.just_pressed(MouseButton::Left) {
-            zone_builder.insert_point(cursor_pos);
+            let point = zone_builder.snap_point(cursor_pos, &snapping);
+
+            zone_builder.insert_point(point);
         }
 
         if keys.just_pressed(KeyCode::Space) {
@@ -186,27 +193,40 @@ pub fn zone_creation_outline_gizmos(
     mut gizmos: Gizmos,
     zones: Query<&SerializableZoneBuilder>,
     cursor_pos: Res<crate::cursor::CursorPosition>,
+    snapping: crate::editor::snapping::Snapping,
 ) {
     zones
         .iter()
         .filter(|SerializableZoneBuilder(SerializableZone { vertices, .. })| vertices.len() > 1)
-        .map(|SerializableZoneBuilder(SerializableZone { vertices, .. })| vertices[1..].to_owned())
-        .for_each(|mut vertices| {
-            if let Some(cursor_pos) = **cursor_pos {
-                vertices.push(cursor_pos)
+        .map(|builder| {
+            (
+                builder.outline().to_owned(),
+                cursor_pos.map(|cursor_pos| builder.snap_point(cursor_pos, &snapping)),
+            )
+        })
+        .for_each(|(mut vertices, next_point)| {
+            if let Some(next_point) = next_point {
+                vertices.push(next_point)
             }
 
+            // Yellow means the zone would cross over itself if it were closed now
+            let color = if super::triangulation::is_self_intersecting(&vertices) {
+                Color::srgb(1.0, 0.8, 0.0)
+            } else {
+                Color::srgb(1.0, 0.0, 0.2)
+            };
+
             vertices.iter().for_each(|vertice| {
                 gizmos.rect_2d(
                     Isometry2d::from_translation(*vertice),
                     Vec2::new(5.0, 5.0),
-                    Color::srgb(1.0, 0.0, 0.2),
+                    color,
                 )
             });
 
             if vertices.len() > 1 {
                 for i in 0..=vertices.len() - 2 {
-                    gizmos.line_2d(vertices[i], vertices[i + 1], Color::srgb(1.0, 0.0, 0.2))
+                    gizmos.line_2d(vertices[i], vertices[i + 1], color)
                 }
             }
         });
@@ -228,8 +248,101 @@ impl Default for SerializableZone {
     }
 }
 
+impl SerializableZone {
+    /// Triangulates an outline in world space around its center, gives back the center too
+    ///
+    /// The first vertex is always the center so zones saved with the old fan indices still line up
+    pub fn from_outline(outline: &[Vec2]) -> (Self, Vec2) {
+        let center = outline.iter().sum::<Vec2>() / outline.len().max(1) as f32;
+
+        let mut outline: Vec<Vec2> = outline.iter().map(|point| *point - center).collect();
+
+        // Counter-clockwise so every zone's triangles face the same way
+        if super::triangulation::signed_area(&outline) < 0.0 {
+            outline.reverse();
+        }
+
+        let indices = match super::triangulation::triangulate(&outline) {
+            Some(triangles) => triangles
+                .into_iter()
+                .flatten()
+                .map(|index| index + 1)
+                .collect(),
+            None => fan_indices(outline.len() as u32),
+        };
+
+        let mut vertices = vec![Vec2::ZERO];
+        vertices.extend(outline);
+
+        (SerializableZone { vertices, indices }, center)
+    }
+
+    /// Every vertex but the center, in order around the zone
+    pub fn outline(&self) -> &[Vec2] {
+        self.vertices.get(1..).unwrap_or_default()
+    }
+
+    pub fn is_self_intersecting(&self) -> bool {
+        super::triangulation::is_self_intersecting(self.outline())
+    }
+
+    pub fn collider(&self, mode: ZoneColliderMode) -> ColliderConstructor {
+        match mode {
+            ZoneColliderMode::Trimesh => ColliderConstructor::Trimesh {
+                vertices: self.vertices.clone(),
+                indices: self
+                    .indices
+                    .chunks_exact(3)
+                    .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                    .collect(),
+            },
+            ZoneColliderMode::Polyline => ColliderConstructor::Polyline {
+                vertices: self.outline().to_vec(),
+                indices: Some(outline_edges(self.outline().len() as u32)),
+            },
+            ZoneColliderMode::ConvexDecomposition => ColliderConstructor::ConvexDecomposition {
+                vertices: self.outline().to_vec(),
+                indices: outline_edges(self.outline().len() as u32),
+            },
+        }
+    }
+}
+
+// Every outline point to the center, only used when ear clipping gives up
+fn fan_indices(outline_len: u32) -> Vec<u32> {
+    (1..=outline_len)
+        .flat_map(|i| [0, i, i % outline_len + 1])
+        .collect()
+}
+
+// Each outline point to the next, looping back to the start
+fn outline_edges(len: u32) -> Vec<[u32; 2]> {
+    (0..len).map(|i| [i, (i + 1) % len]).collect()
+}
+
 impl Into<SerializableZoneMeshBuilder> for SerializableZone {
     fn into(self) -> SerializableZoneMeshBuilder {
         SerializableZoneMeshBuilder(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_intersecting_outline_falls_back_to_a_fan() {
+        let bowtie = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(0.0, 2.0),
+        ];
+
+        let (zone, center) = SerializableZone::from_outline(&bowtie);
+
+        assert_eq!(center, Vec2::ONE);
+        assert!(zone.is_self_intersecting());
+        assert_eq!(zone.indices, fan_indices(4));
+    }
+}
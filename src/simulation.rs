@@ -0,0 +1,376 @@
+use std::path::Path;
+
+use avian2d::prelude::*;
+use bevy::{
+    ecs::{
+        entity::EntityHashMap,
+        system::{RunSystemError, RunSystemOnce},
+    },
+    prelude::*,
+    scene::SceneSpawnError,
+    time::TimeUpdateStrategy,
+};
+
+use crate::{
+    game::{
+        collision::CollisionResponsePlugin,
+        gravity::GravityPlugin,
+        influence::InfluencePlugin,
+        launch::{DynamicObject, DynamicObjectBundle, LaunchingObjectConfig},
+        rails::RailPhysicsPlugin,
+        trigger::{GameTrigger, Triggered},
+    },
+    serialization::{
+        self, StartPoint,
+        colliders::initialize_colliders,
+        game::{LevelReadError, read_level},
+    },
+};
+
+/// Everything needed to run a level's physics without a window, camera or [AssetServer]
+///
+/// Meant to be used with [MinimalPlugins], see [LevelSimulation] for the whole setup
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            serialization::SerializeableTypeRegistrationPlugin,
+            GravityPlugin,
+            CollisionResponsePlugin,
+            RailPhysicsPlugin,
+            InfluencePlugin,
+            PhysicsPlugins::default(),
+        ))
+        .init_resource::<StartPoint>()
+        // Disable Avian Gravity
+        .insert_resource(avian2d::prelude::Gravity::ZERO);
+    }
+}
+
+/// A headless, deterministic run of a single launch through a level
+///
+/// Every call to [LevelSimulation::step] advances exactly one fixed physics tick,
+/// so the results don't depend on how fast the machine is
+pub struct LevelSimulation {
+    app: App,
+    launched: Entity,
+    ticks: u32,
+}
+
+impl LevelSimulation {
+    /// Sets up a world with the level in it and an object launched from its [StartPoint]
+    pub fn new(level: &DynamicScene, launch_velocity: Vec2) -> Result<Self, SimulationError> {
+        let mut app = App::new();
+
+        app.add_plugins((
+            MinimalPlugins,
+            bevy::transform::TransformPlugin,
+            AssetPlugin::default(),
+            // Avian looks for scene colliders, so the spawner has to be there even if it's never used
+            bevy::scene::ScenePlugin,
+            SimulationPlugin,
+        ))
+        // Avian wants this around for colliders made from meshes
+        .init_asset::<Mesh>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ));
+
+        app.finish();
+        app.cleanup();
+
+        // Time doesn't advance on the first update, get it out of the way
+        app.update();
+
+        let world = app.world_mut();
+
+        level.write_to_world(world, &mut EntityHashMap::default())?;
+
+        world.run_system_once(initialize_colliders)?;
+
+        let start_point =
+            (**world.resource::<StartPoint>()).ok_or(SimulationError::NoStartPoint)?;
+
+        let config = LaunchingObjectConfig::default();
+
+        let launched = world
+            .spawn((
+                Transform::from_translation(start_point.extend(0.0)),
+                config.gravity_layers,
+                DynamicObject,
+                DynamicObjectBundle::new(&config, launch_velocity),
+            ))
+            .id();
+
+        Ok(LevelSimulation {
+            app,
+            launched,
+            ticks: 0,
+        })
+    }
+
+    /// Advances the simulation by one fixed tick
+    pub fn step(&mut self) {
+        self.app.update();
+        self.ticks += 1;
+    }
+
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// The object launched from the start point, gone from the world once it dies
+    pub fn launched(&self) -> Entity {
+        self.launched
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.app.world().get_entity(self.launched).is_err()
+    }
+
+    // (hit, total)
+    fn trigger_counts(&mut self) -> (usize, usize) {
+        let world = self.app.world_mut();
+
+        world
+            .query_filtered::<Has<Triggered>, With<GameTrigger>>()
+            .iter(world)
+            .fold((0, 0), |(hit, total), triggered| {
+                (hit + triggered as usize, total + 1)
+            })
+    }
+
+    /// Steps until `max_ticks` is reached, the launched object dies or every trigger is hit
+    pub fn run(mut self, max_ticks: u32) -> SimulationOutcome {
+        while self.ticks < max_ticks {
+            self.step();
+
+            let (hit, total) = self.trigger_counts();
+
+            if self.is_dead() || (total > 0 && hit == total) {
+                break;
+            }
+        }
+
+        self.outcome()
+    }
+
+    pub fn outcome(&mut self) -> SimulationOutcome {
+        let (triggers_hit, trigger_count) = self.trigger_counts();
+
+        let world = self.app.world_mut();
+
+        let final_positions = world
+            .query_filtered::<(Entity, &Position), With<RigidBody>>()
+            .iter(world)
+            .map(|(entity, position)| (entity, position.0))
+            .collect();
+
+        let launched_position = world
+            .get::<Position>(self.launched)
+            .map(|position| position.0);
+
+        SimulationOutcome {
+            ticks: self.ticks,
+            died: self.is_dead(),
+            triggers_hit,
+            trigger_count,
+            launched_position,
+            final_positions,
+        }
+    }
+}
+
+/// What happened during a [LevelSimulation]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationOutcome {
+    pub ticks: u32,
+    pub died: bool,
+    pub triggers_hit: usize,
+    pub trigger_count: usize,
+    // None if it died
+    pub launched_position: Option<Vec2>,
+    // Every rigid body left in the world
+    pub final_positions: Vec<(Entity, Vec2)>,
+}
+
+impl SimulationOutcome {
+    /// Every trigger was hit and the launched object survived
+    pub fn is_win(&self) -> bool {
+        !self.died && self.trigger_count > 0 && self.triggers_hit == self.trigger_count
+    }
+}
+
+/// Loads the level at `path` (a real path, not relative to `assets/`) and simulates one launch
+pub fn simulate_level(
+    path: impl AsRef<Path>,
+    launch_velocity: Vec2,
+    max_ticks: u32,
+) -> Result<SimulationOutcome, SimulationError> {
+    let level = read_level(
+        path,
+        &serialization::serializable_components_type_registry(),
+    )?;
+
+    Ok(LevelSimulation::new(&level, launch_velocity)?.run(max_ticks))
+}
+
+#[derive(Debug)]
+pub enum SimulationError {
+    Read(LevelReadError),
+    Spawn(SceneSpawnError),
+    Setup(RunSystemError),
+    NoStartPoint,
+}
+
+impl std::fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::Read(err) => write!(f, "{err}"),
+            SimulationError::Spawn(err) => write!(f, "Failed to spawn level: {err}"),
+            SimulationError::Setup(err) => write!(f, "Failed to set up simulation: {err}"),
+            SimulationError::NoStartPoint => write!(f, "Level has no start point"),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+impl From<LevelReadError> for SimulationError {
+    fn from(value: LevelReadError) -> Self {
+        SimulationError::Read(value)
+    }
+}
+
+impl From<SceneSpawnError> for SimulationError {
+    fn from(value: SceneSpawnError) -> Self {
+        SimulationError::Spawn(value)
+    }
+}
+
+impl From<RunSystemError> for SimulationError {
+    fn from(value: RunSystemError) -> Self {
+        SimulationError::Setup(value)
+    }
+}
+
+/// A lone planet at the origin pulling on whatever starts at `start`, for testing orbits
+#[cfg(test)]
+pub(crate) fn planet_level(mass: f32, start: Vec2) -> DynamicScene {
+    let source = format!(
+        r#"(
+  resources: {{
+    "bevy_orbit::serialization::StartPoint": (Some(({}, {}))),
+  }},
+  entities: {{
+    4294967296: (
+      components: {{
+        "avian2d::dynamics::rigid_body::RigidBody": Static,
+        "avian2d::dynamics::rigid_body::mass_properties::components::Mass": ({mass}),
+        "bevy_orbit::game::gravity::Gravity": (),
+        "bevy_orbit::serialization::GameSerializable": (),
+        "bevy_transform::components::transform::Transform": (
+          translation: (0.0, 0.0, 0.0),
+          rotation: (0.0, 0.0, 0.0, 1.0),
+          scale: (1.0, 1.0, 1.0),
+        ),
+      }},
+    ),
+  }},
+)"#,
+        start.x, start.y
+    );
+
+    serialization::game::deserialize_level(
+        &source,
+        &serialization::serializable_components_type_registry(),
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One trigger straight ahead of the start point, with nothing pulling on anything
+    const STRAIGHT_SHOT: &str = r#"(
+  resources: {
+    "bevy_orbit::serialization::StartPoint": (Some((-100.0, 0.0))),
+  },
+  entities: {
+    4294967296: (
+      components: {
+        "avian2d::collision::collider::layers::CollisionLayers": (
+          memberships: (2),
+          filters: (1),
+        ),
+        "bevy_orbit::game::trigger::GameTrigger": (),
+        "bevy_orbit::serialization::GameSerializable": (),
+        "bevy_orbit::serialization::colliders::SerializableCollider": (Circle(
+          radius: 10.0,
+        )),
+        "bevy_transform::components::transform::Transform": (
+          translation: (0.0, 0.0, 0.0),
+          rotation: (0.0, 0.0, 0.0, 1.0),
+          scale: (1.0, 1.0, 1.0),
+        ),
+      },
+    ),
+  },
+)"#;
+
+    // About two seconds
+    const MAX_TICKS: u32 = 128;
+
+    fn level2_path() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/test_levels/level2.level.ron")
+    }
+
+    fn simulate_straight_shot(launch_velocity: Vec2) -> SimulationOutcome {
+        // Goes through a real file so [simulate_level] is what gets tested
+        let path = std::env::temp_dir().join(format!(
+            "bevy_orbit_straight_shot_{}_{}.level.ron",
+            std::process::id(),
+            launch_velocity.x
+        ));
+
+        std::fs::write(&path, STRAIGHT_SHOT).unwrap();
+
+        let outcome = simulate_level(&path, launch_velocity, MAX_TICKS);
+
+        std::fs::remove_file(&path).unwrap();
+
+        outcome.unwrap()
+    }
+
+    #[test]
+    fn launching_at_the_trigger_wins() {
+        let outcome = simulate_straight_shot(Vec2::new(200.0, 0.0));
+
+        assert!(outcome.is_win(), "{outcome:?}");
+        assert!(outcome.ticks < MAX_TICKS);
+    }
+
+    #[test]
+    fn launching_away_from_the_trigger_loses() {
+        let outcome = simulate_straight_shot(Vec2::new(-200.0, 0.0));
+
+        assert!(!outcome.is_win(), "{outcome:?}");
+        assert_eq!(outcome.ticks, MAX_TICKS);
+    }
+
+    #[test]
+    fn replays_are_deterministic() {
+        let launch_velocity = Vec2::new(150.0, 40.0);
+
+        let first = simulate_level(level2_path(), launch_velocity, MAX_TICKS * 3).unwrap();
+        let second = simulate_level(level2_path(), launch_velocity, MAX_TICKS * 3).unwrap();
+
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+
+use crate::{
+    serialization::{self, game::read_level},
+    simulation::{LevelSimulation, SimulationError},
+};
+
+/// Which launch vectors to try, angles go all the way around the start point
+pub struct SolverSettings {
+    pub angle_steps: u32,
+    pub min_power: f32,
+    pub max_power: f32,
+    pub power_steps: u32,
+    // How long each launch gets before it counts as a miss
+    pub max_seconds: f32,
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        SolverSettings {
+            angle_steps: 72,
+            min_power: 20.0,
+            max_power: 600.0,
+            power_steps: 30,
+            max_seconds: 10.0,
+        }
+    }
+}
+
+impl SolverSettings {
+    /// Every launch vector in the sweep as (angle, power)
+    pub fn candidates(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        let angle_steps = self.angle_steps.max(1);
+        let power_steps = self.power_steps.max(1);
+
+        (0..angle_steps).flat_map(move |angle_step| {
+            let angle = angle_step as f32 / angle_steps as f32 * std::f32::consts::TAU;
+
+            (0..power_steps).map(move |power_step| {
+                let power = if power_steps == 1 {
+                    self.min_power
+                } else {
+                    self.min_power
+                        + (self.max_power - self.min_power) * power_step as f32
+                            / (power_steps - 1) as f32
+                };
+
+                (angle, power)
+            })
+        })
+    }
+
+    fn max_ticks(&self) -> u32 {
+        (self.max_seconds / Time::<Fixed>::default().timestep().as_secs_f32()).ceil() as u32
+    }
+}
+
+/// A launch vector that touches every trigger without dying
+#[derive(Debug, Clone)]
+pub struct Solution {
+    // Radians, counter clockwise from +x
+    pub angle: f32,
+    pub power: f32,
+    pub velocity: Vec2,
+    // How long it took to hit the last trigger
+    pub ticks: u32,
+}
+
+impl Solution {
+    pub fn seconds(&self) -> f32 {
+        self.ticks as f32 * Time::<Fixed>::default().timestep().as_secs_f32()
+    }
+}
+
+/// Sweeps every candidate launch from the level's start point and keeps the winning ones
+pub fn solve_level(
+    level: &DynamicScene,
+    settings: &SolverSettings,
+) -> Result<Vec<Solution>, SimulationError> {
+    let max_ticks = settings.max_ticks();
+
+    let mut solutions = vec![];
+
+    for (angle, power) in settings.candidates() {
+        let velocity = Vec2::from_angle(angle) * power;
+
+        let outcome = LevelSimulation::new(level, velocity)?.run(max_ticks);
+
+        if outcome.is_win() {
+            solutions.push(Solution {
+                angle,
+                power,
+                velocity,
+                ticks: outcome.ticks,
+            });
+        }
+    }
+
+    Ok(solutions)
+}
+
+/// [solve_level] for a level on disk (a real path, not relative to `assets/`)
+pub fn solve_level_file(
+    path: impl AsRef<Path>,
+    settings: &SolverSettings,
+) -> Result<Vec<Solution>, SimulationError> {
+    let level = read_level(
+        path,
+        &serialization::serializable_components_type_registry(),
+    )?;
+
+    solve_level(&level, settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only a narrow window wins, so coarser sweeps miss it and the full one takes minutes
+    #[test]
+    #[ignore = "sweeps the whole level, run with --ignored"]
+    fn level2_has_a_solution() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/test_levels/level2.level.ron");
+
+        let settings = SolverSettings::default();
+        let solutions = solve_level_file(&path, &settings).unwrap();
+
+        assert!(!solutions.is_empty());
+
+        // Simulations are deterministic, so replaying a solution wins again
+        let outcome =
+            crate::simulation::simulate_level(&path, solutions[0].velocity, settings.max_ticks())
+                .unwrap();
+
+        assert!(outcome.is_win(), "{outcome:?}");
+        assert_eq!(outcome.ticks, solutions[0].ticks);
+    }
+}